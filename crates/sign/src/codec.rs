@@ -0,0 +1,276 @@
+//! String and serde representations of keys and signatures.
+//!
+//! Both are tagged with the scheme so they can be parsed without knowing it in advance.
+//! Strings have the form `<scheme>:<encoded bytes>`, for example `ed25519:9f3a...`.
+
+use std::{fmt::Display, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use zeroize::Zeroizing;
+
+use crate::{
+    decode_slice, encode_slice, public_key_from_bytes, public_key_to_bytes, signature_from_bytes,
+    signature_to_bytes, Encoding, Error, Key, PublicKey, SecretBytes, Signature,
+};
+
+const SECP256K1: &str = "secp256k1";
+const ED25519: &str = "ed25519";
+
+#[derive(Serialize)]
+struct TaggedRef<'a> {
+    scheme: &'a str,
+    #[serde(with = "hex_or_bytes")]
+    data: &'a [u8],
+}
+
+#[derive(Deserialize)]
+struct Tagged {
+    scheme: String,
+    #[serde(with = "hex_or_bytes")]
+    data: Vec<u8>,
+}
+
+impl Signature {
+    /// Encode the signature as `<scheme>:<data>` using the given encoding.
+    ///
+    /// The data is **not** padded to be word aligned.
+    pub fn encode(&self, encoding: Encoding) -> Result<String, Error> {
+        encode_tagged(self.scheme(), &signature_to_bytes(self)?, encoding)
+    }
+
+    /// Decode a signature from `<scheme>:<data>` using the given encoding.
+    pub fn decode(s: &str, encoding: Encoding) -> Result<Self, Error> {
+        let (scheme, data) = decode_tagged(s, encoding)?;
+        Self::from_tagged(scheme, &data)
+    }
+
+    fn scheme(&self) -> &'static str {
+        match self {
+            Signature::Secp256k1(_) => SECP256K1,
+            Signature::Ed25519(_) => ED25519,
+        }
+    }
+
+    fn from_tagged(scheme: &str, data: &[u8]) -> Result<Self, Error> {
+        ensure_known_scheme(scheme)?;
+        let sig = signature_from_bytes(data)?;
+        ensure_scheme(sig.scheme(), scheme)?;
+        Ok(sig)
+    }
+}
+
+impl PublicKey {
+    /// Encode the public key as `<scheme>:<data>` using the given encoding.
+    ///
+    /// The data is **not** padded to be word aligned.
+    pub fn encode(&self, encoding: Encoding) -> Result<String, Error> {
+        encode_tagged(self.scheme(), &public_key_to_bytes(self), encoding)
+    }
+
+    /// Decode a public key from `<scheme>:<data>` using the given encoding.
+    pub fn decode(s: &str, encoding: Encoding) -> Result<Self, Error> {
+        let (scheme, data) = decode_tagged(s, encoding)?;
+        Self::from_tagged(scheme, &data)
+    }
+
+    fn scheme(&self) -> &'static str {
+        match self {
+            PublicKey::Secp256k1(_) => SECP256K1,
+            PublicKey::Ed25519(_) => ED25519,
+        }
+    }
+
+    fn from_tagged(scheme: &str, data: &[u8]) -> Result<Self, Error> {
+        ensure_known_scheme(scheme)?;
+        let key = public_key_from_bytes(data)?;
+        ensure_scheme(key.scheme(), scheme)?;
+        Ok(key)
+    }
+}
+
+impl Key {
+    /// Encode the private key as `<scheme>:<data>` using the given encoding.
+    ///
+    /// Warning: The output contains the secret key.
+    pub fn encode_secret(&self, encoding: Encoding) -> Result<String, Error> {
+        encode_tagged(self.scheme(), self.secret_bytes().expose(), encoding)
+    }
+
+    /// Decode a private key from `<scheme>:<data>` using the given encoding.
+    pub fn decode(s: &str, encoding: Encoding) -> Result<Self, Error> {
+        let (scheme, data) = decode_tagged(s, encoding)?;
+        Self::from_tagged(scheme, &SecretBytes::new(data))
+    }
+
+    fn scheme(&self) -> &'static str {
+        match self {
+            Key::Secp256k1(_) => SECP256K1,
+            Key::Ed25519(_) => ED25519,
+        }
+    }
+
+    fn from_tagged(scheme: &str, data: &SecretBytes) -> Result<Self, Error> {
+        let data = data.expose();
+        match scheme {
+            SECP256K1 => Ok(Key::Secp256k1(secp256k1::SecretKey::from_slice(data)?)),
+            ED25519 => {
+                let key = data
+                    .try_into()
+                    .map_err(|_| Error::InvalidPrivateKeyLength(data.len()))?;
+                Ok(Key::Ed25519(key))
+            }
+            _ => Err(Error::UnknownScheme(scheme.to_string())),
+        }
+    }
+}
+
+impl Display for Signature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = self.encode(Encoding::Hex).map_err(|_| std::fmt::Error)?;
+        write!(f, "{}", s)
+    }
+}
+
+impl Display for PublicKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = self.encode(Encoding::Hex).map_err(|_| std::fmt::Error)?;
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for Signature {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::decode(s, Encoding::Hex)
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::decode(s, Encoding::Hex)
+    }
+}
+
+impl FromStr for Key {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::decode(s, Encoding::Hex)
+    }
+}
+
+impl Serialize for Signature {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let data = signature_to_bytes(self).map_err(serde::ser::Error::custom)?;
+        TaggedRef {
+            scheme: self.scheme(),
+            data: &data,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl Serialize for PublicKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        TaggedRef {
+            scheme: self.scheme(),
+            data: &public_key_to_bytes(self),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Signature {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let Tagged { scheme, data } = Tagged::deserialize(deserializer)?;
+        Self::from_tagged(&scheme, &data).map_err(de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let Tagged { scheme, data } = Tagged::deserialize(deserializer)?;
+        Self::from_tagged(&scheme, &data).map_err(de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let Tagged { scheme, data } = Tagged::deserialize(deserializer)?;
+        Self::from_tagged(&scheme, &SecretBytes::new(data)).map_err(de::Error::custom)
+    }
+}
+
+/// Opt in to serializing a private key.
+///
+/// `Key` does not implement `Serialize` so secrets are not leaked by accident.
+/// Use `#[serde(with = "essential_signer::serde_key")]` on a field to serialize it.
+pub mod serde_key {
+    use super::*;
+
+    /// Serialize a private key.
+    ///
+    /// Warning: The output contains the secret key.
+    pub fn serialize<S: Serializer>(key: &Key, serializer: S) -> Result<S::Ok, S::Error> {
+        TaggedRef {
+            scheme: key.scheme(),
+            data: key.secret_bytes().expose(),
+        }
+        .serialize(serializer)
+    }
+
+    /// Deserialize a private key.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Key, D::Error> {
+        Key::deserialize(deserializer)
+    }
+}
+
+/// Hex strings for human readable formats and raw bytes otherwise.
+mod hex_or_bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&hex::encode(data))
+        } else {
+            serde_bytes::serialize(data, serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        if deserializer.is_human_readable() {
+            let s = Zeroizing::new(String::deserialize(deserializer)?);
+            hex::decode(&*s).map_err(de::Error::custom)
+        } else {
+            serde_bytes::deserialize(deserializer)
+        }
+    }
+}
+
+fn encode_tagged(scheme: &str, data: &[u8], encoding: Encoding) -> Result<String, Error> {
+    Ok(format!("{}:{}", scheme, encode_slice(data, encoding)?))
+}
+
+/// The input is left out of errors as it may be a secret key.
+fn decode_tagged(s: &str, encoding: Encoding) -> Result<(&str, Vec<u8>), Error> {
+    let (scheme, data) = s.split_once(':').ok_or(Error::MissingScheme)?;
+    Ok((scheme, decode_slice(data, encoding)?))
+}
+
+fn ensure_known_scheme(scheme: &str) -> Result<(), Error> {
+    match scheme {
+        SECP256K1 | ED25519 => Ok(()),
+        _ => Err(Error::UnknownScheme(scheme.to_string())),
+    }
+}
+
+fn ensure_scheme(expected: &str, found: &str) -> Result<(), Error> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(Error::SchemeMismatch)
+    }
+}
@@ -0,0 +1,65 @@
+use essential_types::Word;
+
+/// Errors that can occur when signing, verifying and encoding data.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The data is required to be word aligned but is not.
+    #[error("Data is not word aligned")]
+    NotWordAligned,
+    /// The signature did not verify against the data and public key.
+    #[error("Signature failed verification")]
+    VerificationFailed,
+    /// The signature and public key use different schemes.
+    #[error("Signature and public key use different schemes")]
+    SchemeMismatch,
+    /// The operation is not supported by this scheme.
+    #[error("{0} is not supported for {1}")]
+    UnsupportedScheme(&'static str, &'static str),
+    /// The signature has an invalid length.
+    #[error("Invalid signature length: {0}")]
+    InvalidSignatureLength(usize),
+    /// The public key has an invalid length.
+    #[error("Invalid public key length: {0}")]
+    InvalidPublicKeyLength(usize),
+    /// The private key has an invalid length.
+    #[error("Invalid private key length: {0}")]
+    InvalidPrivateKeyLength(usize),
+    /// The scheme name is not known.
+    #[error("Unknown scheme: {0}")]
+    UnknownScheme(String),
+    /// The string is not tagged with a scheme.
+    #[error("Missing scheme. Expected <scheme>:<data>")]
+    MissingScheme,
+    /// The secp256k1 recovery id is out of range.
+    #[error("Invalid recovery id: {0}")]
+    InvalidRecoveryId(Word),
+    /// The last word of a secp256k1 public key is not a single byte.
+    ///
+    /// It holds the last byte of the x coordinate of the compressed key.
+    #[error("Invalid last public key word: {0}. Expected a single byte")]
+    InvalidPublicKeyWord(Word),
+    /// The reader produced a different number of bytes than expected.
+    #[error("Expected to read {0} bytes but read {1}")]
+    ReaderLengthMismatch(u64, u64),
+    /// An error from the secp256k1 library.
+    #[error(transparent)]
+    Secp256k1(#[from] secp256k1::Error),
+    /// An error from the ed25519 library.
+    #[error(transparent)]
+    Ed25519(#[from] ed25519_dalek::SignatureError),
+    /// Failed to read data.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// Failed to serialize data with postcard.
+    #[error(transparent)]
+    Postcard(#[from] postcard::Error),
+    /// Failed to encode or decode json.
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// Failed to decode hex.
+    #[error(transparent)]
+    Hex(#[from] hex::FromHexError),
+    /// Failed to decode base64.
+    #[error(transparent)]
+    Base64(#[from] base64::DecodeError),
+}
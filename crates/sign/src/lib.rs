@@ -4,21 +4,31 @@
 //! It is designed to be used with the Essential protocol.
 //! There is functionality to make word aligning data for use in decision variables easy.
 //!
-//! Note that all hashing in this crate is done with sha256.
-//! This may change in the future to allow more types of hashing.
-//! You can use `sign_hash` if you wish to hash the data with a different algorithm.
+//! By default all hashing in this crate is done with sha256.
+//! The `_with_hasher` variants allow choosing a different `HashAlgorithm`.
+//! You can use `sign_hash` if you wish to hash the data with an algorithm not supported here.
 
 #![deny(missing_docs)]
 #![deny(unsafe_code)]
 
-use anyhow::ensure;
 use clap::ValueEnum;
-use essential_types::{convert::word_4_from_u8_32, Hash, Word};
+use essential_types::{
+    convert::{u8_32_from_word_4, u8_64_from_word_8, word_4_from_u8_32, word_8_from_u8_64},
+    Hash, Word,
+};
 use serde::{Deserialize, Serialize};
 use sha2::Digest;
+use std::io::Read;
 
+mod codec;
+mod error;
+mod secret;
+
+pub use codec::serde_key;
 pub use ed25519_dalek;
+pub use error::Error;
 pub use secp256k1;
+pub use secret::SecretBytes;
 
 #[derive(ValueEnum, Clone, Copy, Debug)]
 /// The encoding to use when decoding or encoding a string.
@@ -48,8 +58,40 @@ pub enum Padding {
     End,
 }
 
-#[derive(Clone, Copy)]
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// The algorithm to use when hashing data before signing.
+///
+/// All algorithms produce a 32 byte hash.
+pub enum HashAlgorithm {
+    /// SHA-256.
+    #[default]
+    Sha256,
+    /// SHA-512/256.
+    /// This is SHA-512 truncated to 32 bytes with distinct initial values.
+    Sha512_256,
+    /// Keccak-256 as used by Ethereum.
+    Keccak256,
+    /// BLAKE2b with a 32 byte output.
+    Blake2b,
+    /// BLAKE3.
+    Blake3,
+}
+
+/// An incremental hasher for any supported `HashAlgorithm`.
+pub struct Hasher(HasherInner);
+
+enum HasherInner {
+    Sha256(sha2::Sha256),
+    Sha512_256(sha2::Sha512_256),
+    Keccak256(sha3::Keccak256),
+    Blake2b(blake2::Blake2b<blake2::digest::consts::U32>),
+    Blake3(Box<blake3::Hasher>),
+}
+
+#[derive(Clone)]
 /// Different types of private keys that can be used for signing.
+///
+/// The key is zeroized when dropped and redacted when debug printed.
 pub enum Key {
     /// A secp256k1 key.
     Secp256k1(secp256k1::SecretKey),
@@ -57,7 +99,7 @@ pub enum Key {
     Ed25519(ed25519_dalek::SecretKey),
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 /// Different types of public keys.
 pub enum PublicKey {
     /// A secp256k1 key.
@@ -75,12 +117,138 @@ pub enum Signature {
     Ed25519(ed25519_dalek::Signature),
 }
 
+impl HashAlgorithm {
+    /// Create an incremental hasher for this algorithm.
+    pub fn hasher(&self) -> Hasher {
+        Hasher::new(*self)
+    }
+
+    /// Hash the data with this algorithm.
+    pub fn hash(&self, data: &[u8]) -> Hash {
+        let mut hasher = self.hasher();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+impl Hasher {
+    /// Create a new hasher for the given algorithm.
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        let inner = match algorithm {
+            HashAlgorithm::Sha256 => HasherInner::Sha256(sha2::Sha256::new()),
+            HashAlgorithm::Sha512_256 => HasherInner::Sha512_256(sha2::Sha512_256::new()),
+            HashAlgorithm::Keccak256 => HasherInner::Keccak256(sha3::Keccak256::new()),
+            HashAlgorithm::Blake2b => HasherInner::Blake2b(blake2::Blake2b::new()),
+            HashAlgorithm::Blake3 => HasherInner::Blake3(Box::new(blake3::Hasher::new())),
+        };
+        Self(inner)
+    }
+
+    /// Add data to the hash.
+    pub fn update(&mut self, data: &[u8]) {
+        match &mut self.0 {
+            HasherInner::Sha256(h) => h.update(data),
+            HasherInner::Sha512_256(h) => h.update(data),
+            HasherInner::Keccak256(h) => h.update(data),
+            HasherInner::Blake2b(h) => h.update(data),
+            HasherInner::Blake3(h) => {
+                h.update(data);
+            }
+        }
+    }
+
+    /// Add all data from a reader to the hash without buffering it all in memory.
+    ///
+    /// Returns the number of bytes read.
+    pub fn update_reader<R: Read>(&mut self, mut reader: R) -> Result<u64, Error> {
+        Ok(std::io::copy(&mut reader, self)?)
+    }
+
+    /// Finish hashing and return the hash.
+    pub fn finalize(self) -> Hash {
+        match self.0 {
+            HasherInner::Sha256(h) => h.finalize().into(),
+            HasherInner::Sha512_256(h) => h.finalize().into(),
+            HasherInner::Keccak256(h) => h.finalize().into(),
+            HasherInner::Blake2b(h) => h.finalize().into(),
+            HasherInner::Blake3(h) => h.finalize().into(),
+        }
+    }
+}
+
+impl std::io::Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Sign data from a reader by padding it to be word aligned and then hashing
+/// with the given algorithm and signing the hash.
+///
+/// The data is hashed as it is read so it is never fully held in memory.
+/// The length of the data must be known in advance so start padding can be applied.
+/// If the data is already word aligned no padding will occur.
+pub fn sign_reader_with_padding<R: Read>(
+    reader: R,
+    len: u64,
+    padding: Padding,
+    hasher: HashAlgorithm,
+    private_key: &Key,
+) -> Result<Signature, Error> {
+    let hash = hash_reader_with_padding(reader, len, padding, hasher)?;
+    sign_hash(hash, private_key)
+}
+
+/// Sign already word aligned data from a reader by hashing
+/// with the given algorithm and signing the hash.
+///
+/// The data is hashed as it is read so it is never fully held in memory.
+/// If the data is not word aligned an error will be returned.
+pub fn sign_aligned_reader<R: Read>(
+    reader: R,
+    hasher: HashAlgorithm,
+    private_key: &Key,
+) -> Result<Signature, Error> {
+    let hash = hash_aligned_reader(reader, hasher)?;
+    sign_hash(hash, private_key)
+}
+
+/// Sign data from a reader by hashing with the given algorithm and signing the hash.
+///
+/// The data is hashed as it is read so it is never fully held in memory.
+/// This does **not** check if the data is word aligned.
+pub fn sign_reader_unchecked<R: Read>(
+    reader: R,
+    hasher: HashAlgorithm,
+    private_key: &Key,
+) -> Result<Signature, Error> {
+    let hash = hash_reader(reader, hasher)?;
+    sign_hash(hash, private_key)
+}
+
 /// Sign data by serializing it using postcard and then hashing and signing the hash.
 ///
 /// This does **not** pad the data to be word aligned.
-pub fn sign_postcard<T: Serialize>(data: &T, private_key: &Key) -> anyhow::Result<Signature> {
+pub fn sign_postcard<T: Serialize>(data: &T, private_key: &Key) -> Result<Signature, Error> {
+    sign_postcard_with_hasher(data, HashAlgorithm::Sha256, private_key)
+}
+
+/// Sign data by serializing it using postcard and then hashing
+/// with the given algorithm and signing the hash.
+///
+/// This does **not** pad the data to be word aligned.
+pub fn sign_postcard_with_hasher<T: Serialize>(
+    data: &T,
+    hasher: HashAlgorithm,
+    private_key: &Key,
+) -> Result<Signature, Error> {
     let data = postcard_bytes(data)?;
-    let hash = hash_bytes(&data)?;
+    let hash = hasher.hash(&data);
     sign_hash(hash, private_key)
 }
 
@@ -91,14 +259,27 @@ pub fn sign_postcard_with_padding<T: Serialize>(
     data: &T,
     padding: Padding,
     private_key: &Key,
-) -> anyhow::Result<Signature> {
+) -> Result<Signature, Error> {
+    sign_postcard_with_padding_and_hasher(data, padding, HashAlgorithm::Sha256, private_key)
+}
+
+/// Sign data by serializing it using postcard and then hashing
+/// with the given algorithm and signing the hash.
+///
+/// This pads the data to be word aligned.
+pub fn sign_postcard_with_padding_and_hasher<T: Serialize>(
+    data: &T,
+    padding: Padding,
+    hasher: HashAlgorithm,
+    private_key: &Key,
+) -> Result<Signature, Error> {
     let data = postcard_bytes_with_padding(data, padding)?;
-    let hash = hash_bytes(&data)?;
+    let hash = hasher.hash(&data);
     sign_hash(hash, private_key)
 }
 
 /// Sign a slice of words by hashing and signing the hash.
-pub fn sign_words(data: &[Word], private_key: &Key) -> anyhow::Result<Signature> {
+pub fn sign_words(data: &[Word], private_key: &Key) -> Result<Signature, Error> {
     let hash = hash_words(data);
     sign_hash(hash, private_key)
 }
@@ -110,31 +291,66 @@ pub fn sign_bytes_with_padding(
     data: Vec<u8>,
     padding: Padding,
     private_key: &Key,
-) -> anyhow::Result<Signature> {
+) -> Result<Signature, Error> {
+    sign_bytes_with_padding_and_hasher(data, padding, HashAlgorithm::Sha256, private_key)
+}
+
+/// Sign the data by padding it to be word aligned and then hashing
+/// with the given algorithm and signing the hash.
+///
+/// If the data is already word aligned no padding will occur.
+pub fn sign_bytes_with_padding_and_hasher(
+    data: Vec<u8>,
+    padding: Padding,
+    hasher: HashAlgorithm,
+    private_key: &Key,
+) -> Result<Signature, Error> {
     let data = align_to_word(data, padding);
-    let hash = hash_bytes(&data)?;
+    let hash = hasher.hash(&data);
     sign_hash(hash, private_key)
 }
 
 /// Sign already word aligned data by hashing and signing the hash.
 ///
 /// If the data is not word aligned an error will be returned.
-pub fn sign_aligned_bytes(data: &[u8], private_key: &Key) -> anyhow::Result<Signature> {
-    ensure!(is_word_aligned(data), "Data is not word aligned");
-    let hash = hash_bytes(data)?;
+pub fn sign_aligned_bytes(data: &[u8], private_key: &Key) -> Result<Signature, Error> {
+    sign_aligned_bytes_with_hasher(data, HashAlgorithm::Sha256, private_key)
+}
+
+/// Sign already word aligned data by hashing with the given algorithm and signing the hash.
+///
+/// If the data is not word aligned an error will be returned.
+pub fn sign_aligned_bytes_with_hasher(
+    data: &[u8],
+    hasher: HashAlgorithm,
+    private_key: &Key,
+) -> Result<Signature, Error> {
+    ensure_word_aligned(data)?;
+    let hash = hasher.hash(data);
     sign_hash(hash, private_key)
 }
 
 /// Sign the data by hashing and signing the hash.
 ///
 /// This does **not** check if the data is word aligned.
-pub fn sign_bytes_unchecked(data: &[u8], private_key: &Key) -> anyhow::Result<Signature> {
-    let hash = hash_bytes(data)?;
+pub fn sign_bytes_unchecked(data: &[u8], private_key: &Key) -> Result<Signature, Error> {
+    sign_bytes_unchecked_with_hasher(data, HashAlgorithm::Sha256, private_key)
+}
+
+/// Sign the data by hashing with the given algorithm and signing the hash.
+///
+/// This does **not** check if the data is word aligned.
+pub fn sign_bytes_unchecked_with_hasher(
+    data: &[u8],
+    hasher: HashAlgorithm,
+    private_key: &Key,
+) -> Result<Signature, Error> {
+    let hash = hasher.hash(data);
     sign_hash(hash, private_key)
 }
 
 /// Sign a already hashed data.
-pub fn sign_hash(hash: Hash, private_key: &Key) -> anyhow::Result<Signature> {
+pub fn sign_hash(hash: Hash, private_key: &Key) -> Result<Signature, Error> {
     match private_key {
         Key::Secp256k1(private_key) => {
             let sig = essential_sign::sign_hash(hash, private_key);
@@ -144,13 +360,333 @@ pub fn sign_hash(hash: Hash, private_key: &Key) -> anyhow::Result<Signature> {
             )?;
             Ok(Signature::Secp256k1(sig))
         }
-        Key::Ed25519(_) => todo!(),
+        Key::Ed25519(private_key) => {
+            use ed25519_dalek::Signer;
+            let key = ed25519_dalek::SigningKey::from_bytes(private_key);
+            Ok(Signature::Ed25519(key.sign(&hash)))
+        }
+    }
+}
+
+/// Verify a signature over data that was serialized using postcard and then hashed.
+///
+/// This does **not** pad the data to be word aligned.
+pub fn verify_postcard<T: Serialize>(
+    data: &T,
+    signature: &Signature,
+    public_key: &PublicKey,
+) -> Result<(), Error> {
+    verify_postcard_with_hasher(data, HashAlgorithm::Sha256, signature, public_key)
+}
+
+/// Verify a signature over data that was serialized using postcard
+/// and then hashed with the given algorithm.
+///
+/// This does **not** pad the data to be word aligned.
+pub fn verify_postcard_with_hasher<T: Serialize>(
+    data: &T,
+    hasher: HashAlgorithm,
+    signature: &Signature,
+    public_key: &PublicKey,
+) -> Result<(), Error> {
+    let data = postcard_bytes(data)?;
+    let hash = hasher.hash(&data);
+    verify_hash(hash, signature, public_key)
+}
+
+/// Verify a signature over data that was serialized using postcard,
+/// padded to be word aligned and then hashed.
+pub fn verify_postcard_with_padding<T: Serialize>(
+    data: &T,
+    padding: Padding,
+    signature: &Signature,
+    public_key: &PublicKey,
+) -> Result<(), Error> {
+    verify_postcard_with_padding_and_hasher(
+        data,
+        padding,
+        HashAlgorithm::Sha256,
+        signature,
+        public_key,
+    )
+}
+
+/// Verify a signature over data that was serialized using postcard,
+/// padded to be word aligned and then hashed with the given algorithm.
+pub fn verify_postcard_with_padding_and_hasher<T: Serialize>(
+    data: &T,
+    padding: Padding,
+    hasher: HashAlgorithm,
+    signature: &Signature,
+    public_key: &PublicKey,
+) -> Result<(), Error> {
+    let data = postcard_bytes_with_padding(data, padding)?;
+    let hash = hasher.hash(&data);
+    verify_hash(hash, signature, public_key)
+}
+
+/// Verify a signature over a hashed slice of words.
+pub fn verify_words(
+    data: &[Word],
+    signature: &Signature,
+    public_key: &PublicKey,
+) -> Result<(), Error> {
+    let hash = hash_words(data);
+    verify_hash(hash, signature, public_key)
+}
+
+/// Verify a signature over data that was padded to be word aligned and then hashed.
+///
+/// If the data is already word aligned no padding will occur.
+pub fn verify_bytes_with_padding(
+    data: Vec<u8>,
+    padding: Padding,
+    signature: &Signature,
+    public_key: &PublicKey,
+) -> Result<(), Error> {
+    verify_bytes_with_padding_and_hasher(
+        data,
+        padding,
+        HashAlgorithm::Sha256,
+        signature,
+        public_key,
+    )
+}
+
+/// Verify a signature over data that was padded to be word aligned
+/// and then hashed with the given algorithm.
+///
+/// If the data is already word aligned no padding will occur.
+pub fn verify_bytes_with_padding_and_hasher(
+    data: Vec<u8>,
+    padding: Padding,
+    hasher: HashAlgorithm,
+    signature: &Signature,
+    public_key: &PublicKey,
+) -> Result<(), Error> {
+    let data = align_to_word(data, padding);
+    let hash = hasher.hash(&data);
+    verify_hash(hash, signature, public_key)
+}
+
+/// Verify a signature over already word aligned data that was hashed.
+///
+/// If the data is not word aligned an error will be returned.
+pub fn verify_aligned_bytes(
+    data: &[u8],
+    signature: &Signature,
+    public_key: &PublicKey,
+) -> Result<(), Error> {
+    verify_aligned_bytes_with_hasher(data, HashAlgorithm::Sha256, signature, public_key)
+}
+
+/// Verify a signature over already word aligned data that was hashed with the given algorithm.
+///
+/// If the data is not word aligned an error will be returned.
+pub fn verify_aligned_bytes_with_hasher(
+    data: &[u8],
+    hasher: HashAlgorithm,
+    signature: &Signature,
+    public_key: &PublicKey,
+) -> Result<(), Error> {
+    ensure_word_aligned(data)?;
+    let hash = hasher.hash(data);
+    verify_hash(hash, signature, public_key)
+}
+
+/// Verify a signature over data that was hashed.
+///
+/// This does **not** check if the data is word aligned.
+pub fn verify_bytes_unchecked(
+    data: &[u8],
+    signature: &Signature,
+    public_key: &PublicKey,
+) -> Result<(), Error> {
+    verify_bytes_unchecked_with_hasher(data, HashAlgorithm::Sha256, signature, public_key)
+}
+
+/// Verify a signature over data that was hashed with the given algorithm.
+///
+/// This does **not** check if the data is word aligned.
+pub fn verify_bytes_unchecked_with_hasher(
+    data: &[u8],
+    hasher: HashAlgorithm,
+    signature: &Signature,
+    public_key: &PublicKey,
+) -> Result<(), Error> {
+    let hash = hasher.hash(data);
+    verify_hash(hash, signature, public_key)
+}
+
+/// Verify a signature over already hashed data.
+///
+/// Returns an error if the signature is invalid or
+/// the signature and public key use different schemes.
+pub fn verify_hash(hash: Hash, signature: &Signature, public_key: &PublicKey) -> Result<(), Error> {
+    match (signature, public_key) {
+        (Signature::Secp256k1(sig), PublicKey::Secp256k1(public_key)) => {
+            let secp = secp256k1::Secp256k1::verification_only();
+            let msg = secp256k1::Message::from_digest(hash);
+            secp.verify_ecdsa(&msg, &sig.to_standard(), public_key)
+                .map_err(|_| Error::VerificationFailed)
+        }
+        (Signature::Ed25519(sig), PublicKey::Ed25519(public_key)) => public_key
+            .verify_strict(&hash, sig)
+            .map_err(|_| Error::VerificationFailed),
+        _ => Err(Error::SchemeMismatch),
+    }
+}
+
+/// Recover the public key from a signature over data that was serialized
+/// using postcard and then hashed.
+///
+/// This does **not** pad the data to be word aligned.
+/// Only secp256k1 signatures support recovery.
+pub fn recover_postcard<T: Serialize>(data: &T, signature: &Signature) -> Result<PublicKey, Error> {
+    recover_postcard_with_hasher(data, HashAlgorithm::Sha256, signature)
+}
+
+/// Recover the public key from a signature over data that was serialized
+/// using postcard and then hashed with the given algorithm.
+///
+/// This does **not** pad the data to be word aligned.
+/// Only secp256k1 signatures support recovery.
+pub fn recover_postcard_with_hasher<T: Serialize>(
+    data: &T,
+    hasher: HashAlgorithm,
+    signature: &Signature,
+) -> Result<PublicKey, Error> {
+    let data = postcard_bytes(data)?;
+    let hash = hasher.hash(&data);
+    recover_hash(hash, signature)
+}
+
+/// Recover the public key from a signature over data that was serialized
+/// using postcard, padded to be word aligned and then hashed.
+///
+/// Only secp256k1 signatures support recovery.
+pub fn recover_postcard_with_padding<T: Serialize>(
+    data: &T,
+    padding: Padding,
+    signature: &Signature,
+) -> Result<PublicKey, Error> {
+    recover_postcard_with_padding_and_hasher(data, padding, HashAlgorithm::Sha256, signature)
+}
+
+/// Recover the public key from a signature over data that was serialized
+/// using postcard, padded to be word aligned and then hashed with the given algorithm.
+///
+/// Only secp256k1 signatures support recovery.
+pub fn recover_postcard_with_padding_and_hasher<T: Serialize>(
+    data: &T,
+    padding: Padding,
+    hasher: HashAlgorithm,
+    signature: &Signature,
+) -> Result<PublicKey, Error> {
+    let data = postcard_bytes_with_padding(data, padding)?;
+    let hash = hasher.hash(&data);
+    recover_hash(hash, signature)
+}
+
+/// Recover the public key from a signature over a hashed slice of words.
+///
+/// Only secp256k1 signatures support recovery.
+pub fn recover_words(data: &[Word], signature: &Signature) -> Result<PublicKey, Error> {
+    let hash = hash_words(data);
+    recover_hash(hash, signature)
+}
+
+/// Recover the public key from a signature over data that was padded
+/// to be word aligned and then hashed.
+///
+/// If the data is already word aligned no padding will occur.
+/// Only secp256k1 signatures support recovery.
+pub fn recover_bytes_with_padding(
+    data: Vec<u8>,
+    padding: Padding,
+    signature: &Signature,
+) -> Result<PublicKey, Error> {
+    recover_bytes_with_padding_and_hasher(data, padding, HashAlgorithm::Sha256, signature)
+}
+
+/// Recover the public key from a signature over data that was padded
+/// to be word aligned and then hashed with the given algorithm.
+///
+/// If the data is already word aligned no padding will occur.
+/// Only secp256k1 signatures support recovery.
+pub fn recover_bytes_with_padding_and_hasher(
+    data: Vec<u8>,
+    padding: Padding,
+    hasher: HashAlgorithm,
+    signature: &Signature,
+) -> Result<PublicKey, Error> {
+    let data = align_to_word(data, padding);
+    let hash = hasher.hash(&data);
+    recover_hash(hash, signature)
+}
+
+/// Recover the public key from a signature over already word aligned data that was hashed.
+///
+/// If the data is not word aligned an error will be returned.
+/// Only secp256k1 signatures support recovery.
+pub fn recover_aligned_bytes(data: &[u8], signature: &Signature) -> Result<PublicKey, Error> {
+    recover_aligned_bytes_with_hasher(data, HashAlgorithm::Sha256, signature)
+}
+
+/// Recover the public key from a signature over already word aligned data
+/// that was hashed with the given algorithm.
+///
+/// If the data is not word aligned an error will be returned.
+/// Only secp256k1 signatures support recovery.
+pub fn recover_aligned_bytes_with_hasher(
+    data: &[u8],
+    hasher: HashAlgorithm,
+    signature: &Signature,
+) -> Result<PublicKey, Error> {
+    ensure_word_aligned(data)?;
+    let hash = hasher.hash(data);
+    recover_hash(hash, signature)
+}
+
+/// Recover the public key from a signature over data that was hashed.
+///
+/// This does **not** check if the data is word aligned.
+/// Only secp256k1 signatures support recovery.
+pub fn recover_bytes_unchecked(data: &[u8], signature: &Signature) -> Result<PublicKey, Error> {
+    recover_bytes_unchecked_with_hasher(data, HashAlgorithm::Sha256, signature)
+}
+
+/// Recover the public key from a signature over data that was hashed with the given algorithm.
+///
+/// This does **not** check if the data is word aligned.
+/// Only secp256k1 signatures support recovery.
+pub fn recover_bytes_unchecked_with_hasher(
+    data: &[u8],
+    hasher: HashAlgorithm,
+    signature: &Signature,
+) -> Result<PublicKey, Error> {
+    let hash = hasher.hash(data);
+    recover_hash(hash, signature)
+}
+
+/// Recover the public key from a signature over already hashed data.
+///
+/// This is the same recovery that the `essential-constraint-vm` performs
+/// with `recover_secp256k1`.
+/// Only secp256k1 signatures support recovery.
+pub fn recover_hash(hash: Hash, signature: &Signature) -> Result<PublicKey, Error> {
+    match signature {
+        Signature::Secp256k1(sig) => {
+            let secp = secp256k1::Secp256k1::verification_only();
+            let msg = secp256k1::Message::from_digest(hash);
+            Ok(PublicKey::Secp256k1(secp.recover_ecdsa(&msg, sig)?))
+        }
+        Signature::Ed25519(_) => Err(Error::UnsupportedScheme("Public key recovery", "ed25519")),
     }
 }
 
 /// Read a file into a vector of bytes.
-pub fn read_file(path: &std::path::Path) -> anyhow::Result<Vec<u8>> {
-    use std::io::Read;
+pub fn read_file(path: &std::path::Path) -> Result<Vec<u8>, Error> {
     let mut file = std::fs::File::open(path)?;
     let mut data = Vec::new();
     file.read_to_end(&mut data)?;
@@ -161,10 +697,15 @@ pub fn read_file(path: &std::path::Path) -> anyhow::Result<Vec<u8>> {
 struct Bytes(#[serde(with = "serde_bytes")] Vec<u8>);
 
 /// Decode a string into a vector of bytes using the given encoding.
-pub fn decode_str(data: String, encoding: Encoding) -> anyhow::Result<Vec<u8>> {
+pub fn decode_str(data: String, encoding: Encoding) -> Result<Vec<u8>, Error> {
+    decode_slice(&data, encoding)
+}
+
+/// Decode without taking ownership so callers decoding secrets control every copy.
+fn decode_slice(data: &str, encoding: Encoding) -> Result<Vec<u8>, Error> {
     match encoding {
         Encoding::Bytes => {
-            let Bytes(data) = serde_json::from_str(&data)?;
+            let Bytes(data) = serde_json::from_str(data)?;
             Ok(data)
         }
         Encoding::Hex | Encoding::HexUpper => Ok(hex::decode(data)?),
@@ -182,9 +723,17 @@ pub fn decode_str(data: String, encoding: Encoding) -> anyhow::Result<Vec<u8>> {
 }
 
 /// Encode a vector of bytes into a string using the given encoding.
-pub fn encode_str(data: Vec<u8>, encoding: Encoding) -> anyhow::Result<String> {
+pub fn encode_str(data: Vec<u8>, encoding: Encoding) -> Result<String, Error> {
+    encode_slice(&data, encoding)
+}
+
+/// Encode a slice of bytes into a string using the given encoding.
+///
+/// Unlike `encode_str` this does not require taking ownership of the data,
+/// so secret bytes are not copied.
+pub fn encode_slice(data: &[u8], encoding: Encoding) -> Result<String, Error> {
     match encoding {
-        Encoding::Bytes => Ok(serde_json::to_string(&Bytes(data))?),
+        Encoding::Bytes => Ok(serde_json::to_string(serde_bytes::Bytes::new(data))?),
         Encoding::Hex => Ok(hex::encode(data)),
         Encoding::HexUpper => Ok(hex::encode_upper(data)),
         Encoding::Base64 => {
@@ -203,13 +752,7 @@ pub fn encode_str(data: Vec<u8>, encoding: Encoding) -> anyhow::Result<String> {
 /// Align and convert the data to words.
 pub fn into_words(data: Vec<u8>, padding: Padding) -> Vec<Word> {
     let data = align_to_word(data, padding);
-    data.chunks(8)
-        .map(|chunk| {
-            essential_types::convert::word_from_bytes(
-                chunk.try_into().expect("This can't fail because of chunks"),
-            )
-        })
-        .collect::<Vec<Word>>()
+    bytes_to_words(&data)
 }
 
 /// Align the data to be word aligned.
@@ -222,9 +765,18 @@ pub fn align_to_word(data: Vec<u8>, padding: Padding) -> Vec<u8> {
     }
 }
 
+/// Return an error if the data is not word aligned.
+fn ensure_word_aligned(data: &[u8]) -> Result<(), Error> {
+    if is_word_aligned(data) {
+        Ok(())
+    } else {
+        Err(Error::NotWordAligned)
+    }
+}
+
 /// Check if the data is word aligned.
 pub fn is_word_aligned(data: &[u8]) -> bool {
-    data.len() % 8 == 0
+    data.len().is_multiple_of(8)
 }
 
 /// Pad the data to be word aligned.
@@ -242,7 +794,7 @@ pub fn pad_bytes(mut data: Vec<u8>, padding: Padding) -> Vec<u8> {
         Padding::End => {
             let len = data.len();
             let pad = 8 - len % 8;
-            data.extend(std::iter::repeat(0).take(pad));
+            data.extend(std::iter::repeat_n(0, pad));
             data
         }
     }
@@ -251,10 +803,67 @@ pub fn pad_bytes(mut data: Vec<u8>, padding: Padding) -> Vec<u8> {
 /// Hash the data using sha256.
 ///
 /// This does **not** pad or check if the data is word aligned.
-pub fn hash_bytes(data: &[u8]) -> anyhow::Result<Hash> {
-    let mut hasher = <sha2::Sha256 as sha2::Digest>::new();
-    hasher.update(data);
-    Ok(hasher.finalize().into())
+pub fn hash_bytes(data: &[u8]) -> Result<Hash, Error> {
+    Ok(HashAlgorithm::Sha256.hash(data))
+}
+
+/// Hash the data using the given algorithm.
+///
+/// This does **not** pad or check if the data is word aligned.
+pub fn hash_bytes_with_hasher(data: &[u8], hasher: HashAlgorithm) -> Hash {
+    hasher.hash(data)
+}
+
+/// Hash data from a reader using the given algorithm.
+///
+/// The data is hashed as it is read so it is never fully held in memory.
+/// This does **not** pad or check if the data is word aligned.
+pub fn hash_reader<R: Read>(reader: R, hasher: HashAlgorithm) -> Result<Hash, Error> {
+    let mut hasher = hasher.hasher();
+    hasher.update_reader(reader)?;
+    Ok(hasher.finalize())
+}
+
+/// Hash already word aligned data from a reader using the given algorithm.
+///
+/// The data is hashed as it is read so it is never fully held in memory.
+/// If the data is not word aligned an error will be returned.
+pub fn hash_aligned_reader<R: Read>(reader: R, hasher: HashAlgorithm) -> Result<Hash, Error> {
+    let mut hasher = hasher.hasher();
+    let len = hasher.update_reader(reader)?;
+    if !len.is_multiple_of(8) {
+        return Err(Error::NotWordAligned);
+    }
+    Ok(hasher.finalize())
+}
+
+/// Hash data from a reader using the given algorithm
+/// as if it was padded to be word aligned.
+///
+/// The data is hashed as it is read so it is never fully held in memory.
+/// The length of the data must be known in advance so start padding can be applied.
+/// An error is returned if the reader does not produce exactly `len` bytes.
+/// If the data is already word aligned no padding will occur.
+pub fn hash_reader_with_padding<R: Read>(
+    reader: R,
+    len: u64,
+    padding: Padding,
+    hasher: HashAlgorithm,
+) -> Result<Hash, Error> {
+    const ZEROS: [u8; 8] = [0; 8];
+    let pad = ((8 - len % 8) % 8) as usize;
+    let mut hasher = hasher.hasher();
+    if let Padding::Start = padding {
+        hasher.update(&ZEROS[..pad]);
+    }
+    let read = hasher.update_reader(reader)?;
+    if read != len {
+        return Err(Error::ReaderLengthMismatch(len, read));
+    }
+    if let Padding::End = padding {
+        hasher.update(&ZEROS[..pad]);
+    }
+    Ok(hasher.finalize())
 }
 
 /// Hash the words using sha256.
@@ -265,11 +874,13 @@ pub fn hash_words(data: &[Word]) -> Hash {
 /// Turn a secp256k1 signature into an essential signature.
 pub fn to_essential_signature(
     sig: secp256k1::ecdsa::RecoverableSignature,
-) -> anyhow::Result<essential_types::Signature> {
+) -> Result<essential_types::Signature, Error> {
     let (rec_id, data) = sig.serialize_compact();
     Ok(essential_types::Signature(
         data,
-        i32::from(rec_id).try_into()?,
+        i32::from(rec_id)
+            .try_into()
+            .map_err(|_| Error::InvalidRecoveryId(i32::from(rec_id).into()))?,
     ))
 }
 
@@ -286,13 +897,15 @@ pub fn signature_to_aligned_bytes(sig: &Signature) -> Vec<u8> {
 /// Turn any supported signature into bytes.
 ///
 /// This is **not** padded to be word aligned.
-pub fn signature_to_bytes(sig: &Signature) -> anyhow::Result<Vec<u8>> {
+pub fn signature_to_bytes(sig: &Signature) -> Result<Vec<u8>, Error> {
     match sig {
         Signature::Secp256k1(sig) => {
             let (rec_id, data) = sig.serialize_compact();
             let mut bytes = data.to_vec();
             let rec_id: i32 = rec_id.into();
-            let rec_id: u8 = rec_id.try_into()?;
+            let rec_id: u8 = rec_id
+                .try_into()
+                .map_err(|_| Error::InvalidRecoveryId(rec_id.into()))?;
             bytes.push(rec_id);
             Ok(bytes)
         }
@@ -305,7 +918,7 @@ pub fn signature_to_bytes(sig: &Signature) -> anyhow::Result<Vec<u8>> {
 /// This can be directly submitted to the api.
 pub fn signed_set_to_bytes(
     signed_set: &essential_types::contract::SignedContract,
-) -> anyhow::Result<Vec<u8>> {
+) -> Result<Vec<u8>, Error> {
     Ok(serde_json::to_vec(signed_set)?)
 }
 
@@ -313,7 +926,7 @@ pub fn signed_set_to_bytes(
 pub fn signature_to_words(sig: &Signature) -> Vec<Word> {
     match sig {
         Signature::Secp256k1(sig) => essential_sign::encode::signature(sig).to_vec(),
-        Signature::Ed25519(_) => todo!(),
+        Signature::Ed25519(sig) => word_8_from_u8_64(sig.to_bytes()).to_vec(),
     }
 }
 
@@ -327,17 +940,153 @@ pub fn public_key_to_words(key: &PublicKey) -> Vec<Word> {
     }
 }
 
+/// Turn any supported public key into bytes.
+///
+/// This is **not** padded to be word aligned.
+/// Secp256k1 keys are in the 33 byte compressed format.
+pub fn public_key_to_bytes(key: &PublicKey) -> Vec<u8> {
+    match key {
+        PublicKey::Secp256k1(key) => key.serialize().to_vec(),
+        PublicKey::Ed25519(key) => key.to_bytes().to_vec(),
+    }
+}
+
+/// Parse a signature from bytes that are **not** padded to be word aligned.
+///
+/// This is the inverse of `signature_to_bytes`.
+/// The scheme is determined by the length of the data:
+/// 65 bytes for secp256k1 and 64 bytes for ed25519.
+pub fn signature_from_bytes(data: &[u8]) -> Result<Signature, Error> {
+    match data.len() {
+        64 => Ok(Signature::Ed25519(ed25519_dalek::Signature::from_slice(
+            data,
+        )?)),
+        65 => secp256k1_signature(&data[..64], data[64].into()),
+        len => Err(Error::InvalidSignatureLength(len)),
+    }
+}
+
+/// Parse a signature from bytes that are padded to be word aligned.
+///
+/// This is the inverse of `signature_to_aligned_bytes`.
+/// The scheme is determined by the length of the data:
+/// 72 bytes for secp256k1 and 64 bytes for ed25519.
+pub fn signature_from_aligned_bytes(data: &[u8]) -> Result<Signature, Error> {
+    ensure_word_aligned(data)?;
+    let words = bytes_to_words(data);
+    signature_from_words(&words)
+}
+
+/// Parse a signature from words.
+///
+/// This is the inverse of `signature_to_words`.
+/// The scheme is determined by the number of words:
+/// 9 words for secp256k1 and 8 words for ed25519.
+pub fn signature_from_words(data: &[Word]) -> Result<Signature, Error> {
+    match data.len() {
+        8 => {
+            let bytes = u8_64_from_word_8(data.try_into().expect("Length is checked"));
+            Ok(Signature::Ed25519(ed25519_dalek::Signature::from_bytes(
+                &bytes,
+            )))
+        }
+        9 => {
+            let bytes = u8_64_from_word_8(data[..8].try_into().expect("Length is checked"));
+            secp256k1_signature(&bytes, data[8])
+        }
+        len => Err(Error::InvalidSignatureLength(len * 8)),
+    }
+}
+
+/// Parse a public key from bytes that are **not** padded to be word aligned.
+///
+/// This is the inverse of `public_key_to_bytes`.
+/// The scheme is determined by the length of the data:
+/// 33 bytes for secp256k1 and 32 bytes for ed25519.
+pub fn public_key_from_bytes(data: &[u8]) -> Result<PublicKey, Error> {
+    match data.len() {
+        32 => {
+            let bytes: [u8; 32] = data.try_into().expect("Length is checked");
+            Ok(PublicKey::Ed25519(ed25519_dalek::VerifyingKey::from_bytes(
+                &bytes,
+            )?))
+        }
+        33 => Ok(PublicKey::Secp256k1(secp256k1::PublicKey::from_slice(
+            data,
+        )?)),
+        len => Err(Error::InvalidPublicKeyLength(len)),
+    }
+}
+
+/// Parse a public key from bytes that are padded to be word aligned.
+///
+/// This is the same layout that the `essential-constraint-vm` expects.
+/// The scheme is determined by the length of the data:
+/// 40 bytes for secp256k1 and 32 bytes for ed25519.
+pub fn public_key_from_aligned_bytes(data: &[u8]) -> Result<PublicKey, Error> {
+    ensure_word_aligned(data)?;
+    let words = bytes_to_words(data);
+    public_key_from_words(&words)
+}
+
+/// Parse a public key from words.
+///
+/// This is the inverse of `public_key_to_words`.
+/// The scheme is determined by the number of words:
+/// 5 words for secp256k1 and 4 words for ed25519.
+pub fn public_key_from_words(data: &[Word]) -> Result<PublicKey, Error> {
+    match data.len() {
+        4 => {
+            let bytes = u8_32_from_word_4(data.try_into().expect("Length is checked"));
+            Ok(PublicKey::Ed25519(ed25519_dalek::VerifyingKey::from_bytes(
+                &bytes,
+            )?))
+        }
+        5 => {
+            let start = u8_32_from_word_4(data[..4].try_into().expect("Length is checked"));
+            let end: u8 = data[4]
+                .try_into()
+                .map_err(|_| Error::InvalidPublicKeyWord(data[4]))?;
+            let mut bytes = start.to_vec();
+            bytes.push(end);
+            Ok(PublicKey::Secp256k1(secp256k1::PublicKey::from_slice(
+                &bytes,
+            )?))
+        }
+        len => Err(Error::InvalidPublicKeyLength(len * 8)),
+    }
+}
+
+fn secp256k1_signature(data: &[u8], rec_id: Word) -> Result<Signature, Error> {
+    let rec_id = i32::try_from(rec_id)
+        .ok()
+        .and_then(|r| secp256k1::ecdsa::RecoveryId::try_from(r).ok())
+        .ok_or(Error::InvalidRecoveryId(rec_id))?;
+    let sig = secp256k1::ecdsa::RecoverableSignature::from_compact(data, rec_id)?;
+    Ok(Signature::Secp256k1(sig))
+}
+
+fn bytes_to_words(data: &[u8]) -> Vec<Word> {
+    data.chunks_exact(8)
+        .map(|chunk| {
+            essential_types::convert::word_from_bytes(
+                chunk.try_into().expect("This can't fail because of chunks"),
+            )
+        })
+        .collect()
+}
+
 /// Serialize data using postcard and then pad it to be word aligned.
 pub fn postcard_bytes_with_padding<T: Serialize>(
     data: &T,
     padding: Padding,
-) -> anyhow::Result<Vec<u8>> {
+) -> Result<Vec<u8>, Error> {
     let data = postcard::to_allocvec(data)?;
     Ok(align_to_word(data, padding))
 }
 
 /// Serialize data using postcard.
-pub fn postcard_bytes<T: Serialize>(data: &T) -> anyhow::Result<Vec<u8>> {
+pub fn postcard_bytes<T: Serialize>(data: &T) -> Result<Vec<u8>, Error> {
     Ok(postcard::to_allocvec(data)?)
 }
 
@@ -0,0 +1,85 @@
+//! Handling of secret key material.
+//!
+//! Secrets are zeroized when dropped and redacted when debug printed.
+
+use std::fmt::Debug;
+
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::Key;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Secret bytes that are zeroized when dropped.
+///
+/// `Debug` prints `[REDACTED]` so the bytes are not logged by accident.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretBytes(Zeroizing<Vec<u8>>);
+
+impl SecretBytes {
+    /// Take ownership of the bytes so they are zeroized when dropped.
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self(Zeroizing::new(bytes))
+    }
+
+    /// Access the secret bytes.
+    ///
+    /// Warning: Avoid copying the bytes out as copies are not zeroized.
+    pub fn expose(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::new(bytes)
+    }
+}
+
+impl From<&[u8]> for SecretBytes {
+    fn from(bytes: &[u8]) -> Self {
+        Self::new(bytes.to_vec())
+    }
+}
+
+impl Debug for SecretBytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SecretBytes").field(&Redacted).finish()
+    }
+}
+
+impl Key {
+    /// Get the raw bytes of the private key.
+    pub fn secret_bytes(&self) -> SecretBytes {
+        match self {
+            Key::Secp256k1(key) => SecretBytes::from(key.as_ref().as_slice()),
+            Key::Ed25519(key) => SecretBytes::from(key.as_slice()),
+        }
+    }
+}
+
+impl Debug for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Key::Secp256k1(_) => f.debug_tuple("Secp256k1").field(&Redacted).finish(),
+            Key::Ed25519(_) => f.debug_tuple("Ed25519").field(&Redacted).finish(),
+        }
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        match self {
+            Key::Secp256k1(key) => key.non_secure_erase(),
+            Key::Ed25519(key) => key.zeroize(),
+        }
+    }
+}
+
+struct Redacted;
+
+impl Debug for Redacted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
@@ -0,0 +1,144 @@
+use essential_signer::{
+    public_key, public_key_from_aligned_bytes, public_key_from_bytes, public_key_from_words,
+    public_key_to_bytes, public_key_to_words, sign_hash, signature_from_aligned_bytes,
+    signature_from_bytes, signature_from_words, signature_to_aligned_bytes, signature_to_bytes,
+    signature_to_words, Error, Signature,
+};
+use essential_types::{convert::bytes_from_word, Word};
+
+mod common;
+
+use common::keys;
+
+fn words_to_bytes(words: &[Word]) -> Vec<u8> {
+    words.iter().copied().flat_map(bytes_from_word).collect()
+}
+
+#[test]
+fn signature_round_trip() {
+    for key in keys() {
+        let sig = sign_hash([3; 32], &key).unwrap();
+
+        let bytes = signature_to_bytes(&sig).unwrap();
+        assert_eq!(signature_from_bytes(&bytes).unwrap(), sig);
+
+        let aligned = signature_to_aligned_bytes(&sig);
+        assert_eq!(aligned.len() % 8, 0);
+        assert_eq!(signature_from_aligned_bytes(&aligned).unwrap(), sig);
+
+        let words = signature_to_words(&sig);
+        assert_eq!(words_to_bytes(&words), aligned);
+        assert_eq!(signature_from_words(&words).unwrap(), sig);
+    }
+}
+
+#[test]
+fn signature_lengths() {
+    let [secp, ed] = keys();
+    let secp = sign_hash([3; 32], &secp).unwrap();
+    let ed = sign_hash([3; 32], &ed).unwrap();
+    assert_eq!(signature_to_bytes(&secp).unwrap().len(), 65);
+    assert_eq!(signature_to_bytes(&ed).unwrap().len(), 64);
+    assert_eq!(signature_to_aligned_bytes(&secp).len(), 72);
+    assert_eq!(signature_to_aligned_bytes(&ed).len(), 64);
+    assert!(matches!(secp, Signature::Secp256k1(_)));
+    assert!(matches!(ed, Signature::Ed25519(_)));
+}
+
+#[test]
+fn public_key_round_trip() {
+    for key in keys() {
+        let pk = public_key(&key);
+
+        let bytes = public_key_to_bytes(&pk);
+        assert_eq!(public_key_from_bytes(&bytes).unwrap(), pk);
+
+        let words = public_key_to_words(&pk);
+        assert_eq!(public_key_from_words(&words).unwrap(), pk);
+        assert_eq!(
+            public_key_from_aligned_bytes(&words_to_bytes(&words)).unwrap(),
+            pk
+        );
+    }
+}
+
+#[test]
+fn invalid_signature_length() {
+    for len in [0, 63, 66, 72] {
+        assert!(matches!(
+            signature_from_bytes(&vec![0; len]),
+            Err(Error::InvalidSignatureLength(l)) if l == len
+        ));
+    }
+    for len in [0, 7, 10] {
+        assert!(matches!(
+            signature_from_words(&vec![0; len]),
+            Err(Error::InvalidSignatureLength(l)) if l == len * 8
+        ));
+    }
+    assert!(matches!(
+        signature_from_aligned_bytes(&[0; 80]),
+        Err(Error::InvalidSignatureLength(80))
+    ));
+    assert!(matches!(
+        signature_from_aligned_bytes(&[0; 65]),
+        Err(Error::NotWordAligned)
+    ));
+}
+
+#[test]
+fn invalid_public_key_length() {
+    for len in [0, 31, 34, 40] {
+        assert!(matches!(
+            public_key_from_bytes(&vec![0; len]),
+            Err(Error::InvalidPublicKeyLength(l)) if l == len
+        ));
+    }
+    for len in [0, 3, 6] {
+        assert!(matches!(
+            public_key_from_words(&vec![0; len]),
+            Err(Error::InvalidPublicKeyLength(l)) if l == len * 8
+        ));
+    }
+    assert!(matches!(
+        public_key_from_aligned_bytes(&[0; 48]),
+        Err(Error::InvalidPublicKeyLength(48))
+    ));
+    assert!(matches!(
+        public_key_from_aligned_bytes(&[0; 33]),
+        Err(Error::NotWordAligned)
+    ));
+}
+
+#[test]
+fn invalid_recovery_id() {
+    let [secp, _] = keys();
+    let sig = sign_hash([3; 32], &secp).unwrap();
+
+    let mut bytes = signature_to_bytes(&sig).unwrap();
+    bytes[64] = 4;
+    assert!(matches!(
+        signature_from_bytes(&bytes),
+        Err(Error::InvalidRecoveryId(4))
+    ));
+
+    let mut words = signature_to_words(&sig);
+    for rec_id in [4, -1, Word::MAX] {
+        words[8] = rec_id;
+        assert!(matches!(
+            signature_from_words(&words),
+            Err(Error::InvalidRecoveryId(r)) if r == rec_id
+        ));
+    }
+}
+
+#[test]
+fn invalid_public_key_word() {
+    let [secp, _] = keys();
+    let mut words = public_key_to_words(&public_key(&secp));
+    words[4] = 256;
+    assert!(matches!(
+        public_key_from_words(&words),
+        Err(Error::InvalidPublicKeyWord(256))
+    ));
+}
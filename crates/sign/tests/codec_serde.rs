@@ -0,0 +1,127 @@
+use essential_signer::{public_key, sign_hash, Encoding, Error, Key, PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+
+mod common;
+
+use common::keys;
+
+fn secret(key: &Key) -> Vec<u8> {
+    key.secret_bytes().expose().to_vec()
+}
+
+#[derive(Serialize, Deserialize)]
+struct Stored {
+    #[serde(with = "essential_signer::serde_key")]
+    key: Key,
+    public_key: PublicKey,
+    signature: Signature,
+}
+
+#[test]
+fn display_from_str_round_trip() {
+    for (key, scheme) in keys().into_iter().zip(["secp256k1", "ed25519"]) {
+        let pk = public_key(&key);
+        let sig = sign_hash([3; 32], &key).unwrap();
+
+        let s = pk.to_string();
+        assert!(s.starts_with(&format!("{}:", scheme)));
+        assert_eq!(s.parse::<PublicKey>().unwrap(), pk);
+
+        let s = sig.to_string();
+        assert!(s.starts_with(&format!("{}:", scheme)));
+        assert_eq!(s.parse::<Signature>().unwrap(), sig);
+
+        let s = key.encode_secret(Encoding::Hex).unwrap();
+        assert_eq!(secret(&s.parse::<Key>().unwrap()), secret(&key));
+    }
+}
+
+#[test]
+fn encodings_round_trip() {
+    for key in keys() {
+        let pk = public_key(&key);
+        let sig = sign_hash([3; 32], &key).unwrap();
+        for encoding in [
+            Encoding::Hex,
+            Encoding::HexUpper,
+            Encoding::Base64,
+            Encoding::Bytes,
+        ] {
+            let s = pk.encode(encoding).unwrap();
+            assert_eq!(PublicKey::decode(&s, encoding).unwrap(), pk);
+            let s = sig.encode(encoding).unwrap();
+            assert_eq!(Signature::decode(&s, encoding).unwrap(), sig);
+            let s = key.encode_secret(encoding).unwrap();
+            assert_eq!(secret(&Key::decode(&s, encoding).unwrap()), secret(&key));
+        }
+    }
+}
+
+#[test]
+fn serde_round_trip() {
+    for key in keys() {
+        let pk = public_key(&key);
+        let sig = sign_hash([3; 32], &key).unwrap();
+        let stored = Stored {
+            key: Key::decode(&key.encode_secret(Encoding::Hex).unwrap(), Encoding::Hex).unwrap(),
+            public_key: pk,
+            signature: sig.clone(),
+        };
+
+        let json = serde_json::to_string(&stored).unwrap();
+        let from_json: Stored = serde_json::from_str(&json).unwrap();
+        assert_eq!(secret(&from_json.key), secret(&key));
+        assert_eq!(from_json.public_key, pk);
+        assert_eq!(from_json.signature, sig);
+
+        let bytes = postcard::to_allocvec(&stored).unwrap();
+        let from_bytes: Stored = postcard::from_bytes(&bytes).unwrap();
+        assert_eq!(secret(&from_bytes.key), secret(&key));
+        assert_eq!(from_bytes.public_key, pk);
+        assert_eq!(from_bytes.signature, sig);
+    }
+}
+
+#[test]
+fn invalid_strings() {
+    let [secp, ed] = keys();
+    let secp_pk = public_key(&secp).to_string();
+    let ed_pk = public_key(&ed).to_string();
+
+    assert!(matches!(
+        secp_pk
+            .trim_start_matches("secp256k1:")
+            .parse::<PublicKey>(),
+        Err(Error::MissingScheme)
+    ));
+    assert!(matches!(
+        secp_pk.replace("secp256k1", "rsa").parse::<PublicKey>(),
+        Err(Error::UnknownScheme(s)) if s == "rsa"
+    ));
+    // The data must match the scheme it is tagged with.
+    assert!(ed_pk
+        .replace("ed25519", "secp256k1")
+        .parse::<PublicKey>()
+        .is_err());
+    assert!("ed25519:0011".parse::<Key>().is_err());
+    assert!(serde_json::from_str::<PublicKey>(r#"{"scheme":"ed25519","data":"zz"}"#).is_err());
+}
+
+#[test]
+fn invalid_key_strings_are_not_in_errors() {
+    for key in keys() {
+        let s = key.encode_secret(Encoding::Hex).unwrap();
+        let (_, data) = s.split_once(':').unwrap();
+        let invalid = [
+            data.to_string(),
+            format!("rsa:{}", data),
+            format!("ed25519:{}00", data),
+            format!("{}{}", s, data),
+        ];
+        for invalid in invalid {
+            let err = invalid.parse::<Key>().unwrap_err();
+            assert!(!err.to_string().contains(data), "{}", err);
+            assert!(!format!("{:?}", err).contains(data), "{:?}", err);
+        }
+    }
+}
@@ -0,0 +1,9 @@
+use essential_signer::{secp256k1, Key};
+
+/// A secp256k1 key and an ed25519 key.
+pub fn keys() -> [Key; 2] {
+    [
+        Key::Secp256k1(secp256k1::SecretKey::from_slice(&[1; 32]).unwrap()),
+        Key::Ed25519([2; 32]),
+    ]
+}
@@ -0,0 +1,142 @@
+use clap::ValueEnum;
+use essential_signer::{
+    public_key, recover_aligned_bytes_with_hasher, recover_bytes_unchecked_with_hasher,
+    recover_bytes_with_padding_and_hasher, recover_postcard_with_hasher,
+    recover_postcard_with_padding_and_hasher, sign_aligned_bytes_with_hasher,
+    sign_bytes_unchecked_with_hasher, sign_bytes_with_padding_and_hasher,
+    sign_postcard_with_hasher, sign_postcard_with_padding_and_hasher, verify_aligned_bytes,
+    verify_aligned_bytes_with_hasher, verify_bytes_unchecked, verify_bytes_unchecked_with_hasher,
+    verify_bytes_with_padding_and_hasher, verify_postcard_with_hasher,
+    verify_postcard_with_padding_and_hasher, Error, HashAlgorithm, Padding,
+};
+
+mod common;
+
+use common::keys;
+
+/// The published digests of "abc".
+fn abc_digest(hasher: HashAlgorithm) -> &'static str {
+    match hasher {
+        HashAlgorithm::Sha256 => "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        HashAlgorithm::Sha512_256 => {
+            "53048e2681941ef99b2e29b76b4c7dabe4c2d0c634fc6d46e0e2f13107e7af23"
+        }
+        HashAlgorithm::Keccak256 => {
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+        }
+        HashAlgorithm::Blake2b => {
+            "bddd813c634239723171ef3fee98579b94964e3bb1cb3e427262c8c068d52319"
+        }
+        HashAlgorithm::Blake3 => "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+    }
+}
+
+#[test]
+fn known_answers() {
+    for &hasher in HashAlgorithm::value_variants() {
+        assert_eq!(
+            hex::encode(hasher.hash(b"abc")),
+            abc_digest(hasher),
+            "{:?}",
+            hasher
+        );
+        let mut incremental = hasher.hasher();
+        incremental.update(b"a");
+        incremental.update(b"bc");
+        assert_eq!(hex::encode(incremental.finalize()), abc_digest(hasher));
+    }
+}
+
+#[test]
+fn bytes_with_hasher_round_trip() {
+    let data = [7u8; 16];
+    for key in keys() {
+        let pk = public_key(&key);
+        for &hasher in HashAlgorithm::value_variants() {
+            let sig = sign_bytes_unchecked_with_hasher(&data, hasher, &key).unwrap();
+            verify_bytes_unchecked_with_hasher(&data, hasher, &sig, &pk).unwrap();
+
+            let sig = sign_aligned_bytes_with_hasher(&data, hasher, &key).unwrap();
+            verify_aligned_bytes_with_hasher(&data, hasher, &sig, &pk).unwrap();
+
+            let sig = sign_bytes_with_padding_and_hasher(vec![7; 5], Padding::Start, hasher, &key)
+                .unwrap();
+            verify_bytes_with_padding_and_hasher(vec![7; 5], Padding::Start, hasher, &sig, &pk)
+                .unwrap();
+        }
+    }
+}
+
+#[test]
+fn postcard_with_hasher_round_trip() {
+    let data = (1u64, "essential".to_string());
+    for key in keys() {
+        let pk = public_key(&key);
+        for &hasher in HashAlgorithm::value_variants() {
+            let sig = sign_postcard_with_hasher(&data, hasher, &key).unwrap();
+            verify_postcard_with_hasher(&data, hasher, &sig, &pk).unwrap();
+
+            let sig =
+                sign_postcard_with_padding_and_hasher(&data, Padding::End, hasher, &key).unwrap();
+            verify_postcard_with_padding_and_hasher(&data, Padding::End, hasher, &sig, &pk)
+                .unwrap();
+        }
+    }
+}
+
+#[test]
+fn other_hasher_fails_to_verify() {
+    let data = [7u8; 16];
+    for key in keys() {
+        let pk = public_key(&key);
+        let sig = sign_bytes_unchecked_with_hasher(&data, HashAlgorithm::Keccak256, &key).unwrap();
+        assert!(matches!(
+            verify_bytes_unchecked(&data, &sig, &pk),
+            Err(Error::VerificationFailed)
+        ));
+        assert!(matches!(
+            verify_aligned_bytes(&data, &sig, &pk),
+            Err(Error::VerificationFailed)
+        ));
+    }
+}
+
+#[test]
+fn recover_with_hasher() {
+    let [key, _] = keys();
+    let pk = public_key(&key);
+    let data = [7u8; 16];
+    let postcard = (1u64, "essential".to_string());
+    for &hasher in HashAlgorithm::value_variants() {
+        let sig = sign_bytes_unchecked_with_hasher(&data, hasher, &key).unwrap();
+        assert_eq!(
+            recover_bytes_unchecked_with_hasher(&data, hasher, &sig).unwrap(),
+            pk
+        );
+        assert_eq!(
+            recover_aligned_bytes_with_hasher(&data, hasher, &sig).unwrap(),
+            pk
+        );
+
+        let sig =
+            sign_bytes_with_padding_and_hasher(vec![7; 5], Padding::End, hasher, &key).unwrap();
+        assert_eq!(
+            recover_bytes_with_padding_and_hasher(vec![7; 5], Padding::End, hasher, &sig).unwrap(),
+            pk
+        );
+
+        let sig = sign_postcard_with_hasher(&postcard, hasher, &key).unwrap();
+        assert_eq!(
+            recover_postcard_with_hasher(&postcard, hasher, &sig).unwrap(),
+            pk
+        );
+
+        let sig =
+            sign_postcard_with_padding_and_hasher(&postcard, Padding::Start, hasher, &key).unwrap();
+        assert_eq!(
+            recover_postcard_with_padding_and_hasher(&postcard, Padding::Start, hasher, &sig)
+                .unwrap(),
+            pk
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use clap::ValueEnum;
+use essential_signer::{
+    align_to_word, hash_aligned_reader, hash_bytes, hash_bytes_with_hasher, hash_reader,
+    hash_reader_with_padding, public_key, sign_reader_with_padding,
+    verify_bytes_with_padding_and_hasher, Error, HashAlgorithm, Padding,
+};
+
+mod common;
+
+use common::keys;
+
+fn data(len: usize) -> Vec<u8> {
+    (0..len).map(|i| i as u8).collect()
+}
+
+#[test]
+fn padded_reader_matches_padded_bytes() {
+    for len in [0, 1, 7, 8, 13, 16, 8191] {
+        let data = data(len);
+        for padding in [Padding::Start, Padding::End] {
+            for &hasher in HashAlgorithm::value_variants() {
+                let expected =
+                    hash_bytes_with_hasher(&align_to_word(data.clone(), padding), hasher);
+                let hash =
+                    hash_reader_with_padding(data.as_slice(), len as u64, padding, hasher).unwrap();
+                assert_eq!(hash, expected, "len {} {:?} {:?}", len, padding, hasher);
+            }
+            let sha256 = hash_bytes(&align_to_word(data.clone(), padding)).unwrap();
+            let hash = hash_reader_with_padding(
+                data.as_slice(),
+                len as u64,
+                padding,
+                HashAlgorithm::Sha256,
+            )
+            .unwrap();
+            assert_eq!(hash, sha256);
+        }
+    }
+}
+
+#[test]
+fn padding_side_matters() {
+    let data = data(13);
+    let start =
+        hash_reader_with_padding(data.as_slice(), 13, Padding::Start, HashAlgorithm::Sha256);
+    let end = hash_reader_with_padding(data.as_slice(), 13, Padding::End, HashAlgorithm::Sha256);
+    assert_ne!(start.unwrap(), end.unwrap());
+}
+
+#[test]
+fn reader_length_mismatch() {
+    let data = data(13);
+    for padding in [Padding::Start, Padding::End] {
+        assert!(matches!(
+            hash_reader_with_padding(data.as_slice(), 16, padding, HashAlgorithm::Sha256),
+            Err(Error::ReaderLengthMismatch(16, 13))
+        ));
+        assert!(matches!(
+            hash_reader_with_padding(data.as_slice(), 8, padding, HashAlgorithm::Sha256),
+            Err(Error::ReaderLengthMismatch(8, 13))
+        ));
+    }
+}
+
+#[test]
+fn unpadded_readers() {
+    for &hasher in HashAlgorithm::value_variants() {
+        let aligned = data(16);
+        assert_eq!(
+            hash_aligned_reader(aligned.as_slice(), hasher).unwrap(),
+            hash_bytes_with_hasher(&aligned, hasher)
+        );
+        let unaligned = data(13);
+        assert!(matches!(
+            hash_aligned_reader(unaligned.as_slice(), hasher),
+            Err(Error::NotWordAligned)
+        ));
+        assert_eq!(
+            hash_reader(unaligned.as_slice(), hasher).unwrap(),
+            hash_bytes_with_hasher(&unaligned, hasher)
+        );
+    }
+}
+
+#[test]
+fn sign_padded_reader() {
+    let data = data(13);
+    for key in keys() {
+        let sig = sign_reader_with_padding(
+            data.as_slice(),
+            13,
+            Padding::Start,
+            HashAlgorithm::Blake2b,
+            &key,
+        )
+        .unwrap();
+        verify_bytes_with_padding_and_hasher(
+            data.clone(),
+            Padding::Start,
+            HashAlgorithm::Blake2b,
+            &sig,
+            &public_key(&key),
+        )
+        .unwrap();
+    }
+}
@@ -0,0 +1,65 @@
+use essential_signer::{
+    public_key, recover_aligned_bytes, recover_bytes_unchecked, recover_bytes_with_padding,
+    recover_hash, recover_postcard, recover_postcard_with_padding, recover_words,
+    sign_aligned_bytes, sign_bytes_unchecked, sign_bytes_with_padding, sign_hash, sign_postcard,
+    sign_postcard_with_padding, sign_words, Error, Padding,
+};
+
+mod common;
+
+use common::keys;
+
+#[test]
+fn recover_signer() {
+    let [key, _] = keys();
+    let pk = public_key(&key);
+
+    let sig = sign_hash([3; 32], &key).unwrap();
+    assert_eq!(recover_hash([3; 32], &sig).unwrap(), pk);
+
+    let data = vec![5u8; 13];
+    let sig = sign_bytes_with_padding(data.clone(), Padding::Start, &key).unwrap();
+    assert_eq!(
+        recover_bytes_with_padding(data.clone(), Padding::Start, &sig).unwrap(),
+        pk
+    );
+    let sig = sign_bytes_unchecked(&data, &key).unwrap();
+    assert_eq!(recover_bytes_unchecked(&data, &sig).unwrap(), pk);
+    let sig = sign_aligned_bytes(&[5; 16], &key).unwrap();
+    assert_eq!(recover_aligned_bytes(&[5; 16], &sig).unwrap(), pk);
+
+    let sig = sign_words(&[1, 2, 3], &key).unwrap();
+    assert_eq!(recover_words(&[1, 2, 3], &sig).unwrap(), pk);
+
+    let postcard = (7u64, "essential".to_string());
+    let sig = sign_postcard(&postcard, &key).unwrap();
+    assert_eq!(recover_postcard(&postcard, &sig).unwrap(), pk);
+    let sig = sign_postcard_with_padding(&postcard, Padding::End, &key).unwrap();
+    assert_eq!(
+        recover_postcard_with_padding(&postcard, Padding::End, &sig).unwrap(),
+        pk
+    );
+}
+
+#[test]
+fn tampered_input_recovers_other_key() {
+    let [key, _] = keys();
+    let sig = sign_hash([3; 32], &key).unwrap();
+    // Recovery succeeds for any message but yields a different key.
+    let recovered = recover_hash([4; 32], &sig).unwrap();
+    assert_ne!(recovered, public_key(&key));
+    assert!(matches!(
+        recover_aligned_bytes(&[5; 13], &sig),
+        Err(Error::NotWordAligned)
+    ));
+}
+
+#[test]
+fn ed25519_is_unsupported() {
+    let [_, key] = keys();
+    let sig = sign_hash([3; 32], &key).unwrap();
+    assert!(matches!(
+        recover_hash([3; 32], &sig),
+        Err(Error::UnsupportedScheme(_, "ed25519"))
+    ));
+}
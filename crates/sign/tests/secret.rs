@@ -0,0 +1,30 @@
+use essential_signer::SecretBytes;
+
+mod common;
+
+use common::keys;
+
+/// Fail if the debug output contains the secret as bytes or hex.
+fn redacted(debug: &str, secret: &[u8]) {
+    assert!(debug.contains("[REDACTED]"), "{}", debug);
+    assert!(!debug.contains(&hex::encode(secret)), "{}", debug);
+    assert!(!debug.contains(&hex::encode_upper(secret)), "{}", debug);
+    assert!(!debug.contains(&format!("{:?}", secret)), "{}", debug);
+    assert!(!debug.contains(&format!("{:#?}", secret)), "{}", debug);
+}
+
+#[test]
+fn debug_is_redacted() {
+    for key in keys() {
+        let secret = key.secret_bytes();
+        for debug in [format!("{:?}", key), format!("{:#?}", key)] {
+            redacted(&debug, secret.expose());
+        }
+        for debug in [format!("{:?}", secret), format!("{:#?}", secret)] {
+            redacted(&debug, secret.expose());
+        }
+        redacted(&format!("{:?}", Some(&key)), secret.expose());
+    }
+    let secret = SecretBytes::new(vec![3; 32]);
+    assert_eq!(format!("{:?}", secret), "SecretBytes([REDACTED])");
+}
@@ -0,0 +1,123 @@
+use essential_signer::{
+    public_key, sign_aligned_bytes, sign_bytes_unchecked, sign_bytes_with_padding, sign_hash,
+    sign_postcard, sign_postcard_with_padding, sign_words, verify_aligned_bytes,
+    verify_bytes_unchecked, verify_bytes_with_padding, verify_hash, verify_postcard,
+    verify_postcard_with_padding, verify_words, Error, Key, Padding,
+};
+
+mod common;
+
+use common::keys;
+
+fn failed(result: Result<(), Error>) {
+    assert!(
+        matches!(result, Err(Error::VerificationFailed)),
+        "expected verification to fail, got {:?}",
+        result
+    );
+}
+
+#[test]
+fn verify_hashes() {
+    for key in keys() {
+        let pk = public_key(&key);
+        let sig = sign_hash([3; 32], &key).unwrap();
+        verify_hash([3; 32], &sig, &pk).unwrap();
+        failed(verify_hash([4; 32], &sig, &pk));
+    }
+}
+
+#[test]
+fn verify_bytes() {
+    let data = vec![5u8; 13];
+    let mut tampered = data.clone();
+    tampered[0] ^= 1;
+    for key in keys() {
+        let pk = public_key(&key);
+
+        let sig = sign_bytes_with_padding(data.clone(), Padding::End, &key).unwrap();
+        verify_bytes_with_padding(data.clone(), Padding::End, &sig, &pk).unwrap();
+        failed(verify_bytes_with_padding(
+            tampered.clone(),
+            Padding::End,
+            &sig,
+            &pk,
+        ));
+        failed(verify_bytes_with_padding(
+            data.clone(),
+            Padding::Start,
+            &sig,
+            &pk,
+        ));
+
+        let sig = sign_bytes_unchecked(&data, &key).unwrap();
+        verify_bytes_unchecked(&data, &sig, &pk).unwrap();
+        failed(verify_bytes_unchecked(&tampered, &sig, &pk));
+
+        let aligned = [5u8; 16];
+        let sig = sign_aligned_bytes(&aligned, &key).unwrap();
+        verify_aligned_bytes(&aligned, &sig, &pk).unwrap();
+        failed(verify_aligned_bytes(&[6u8; 16], &sig, &pk));
+        assert!(matches!(
+            verify_aligned_bytes(&data, &sig, &pk),
+            Err(Error::NotWordAligned)
+        ));
+    }
+}
+
+#[test]
+fn verify_words_and_postcard() {
+    let data = (7u64, "essential".to_string());
+    let tampered = (8u64, "essential".to_string());
+    for key in keys() {
+        let pk = public_key(&key);
+
+        let sig = sign_words(&[1, 2, 3], &key).unwrap();
+        verify_words(&[1, 2, 3], &sig, &pk).unwrap();
+        failed(verify_words(&[1, 2, 4], &sig, &pk));
+
+        let sig = sign_postcard(&data, &key).unwrap();
+        verify_postcard(&data, &sig, &pk).unwrap();
+        failed(verify_postcard(&tampered, &sig, &pk));
+
+        let sig = sign_postcard_with_padding(&data, Padding::Start, &key).unwrap();
+        verify_postcard_with_padding(&data, Padding::Start, &sig, &pk).unwrap();
+        failed(verify_postcard_with_padding(
+            &tampered,
+            Padding::Start,
+            &sig,
+            &pk,
+        ));
+    }
+}
+
+#[test]
+fn wrong_key_fails() {
+    let other = [
+        Key::Secp256k1(secp256k1::SecretKey::from_slice(&[9; 32]).unwrap()),
+        Key::Ed25519([9; 32]),
+    ];
+    for (key, other) in keys().iter().zip(&other) {
+        let sig = sign_hash([3; 32], key).unwrap();
+        failed(verify_hash([3; 32], &sig, &public_key(other)));
+    }
+}
+
+#[test]
+fn scheme_mismatch() {
+    let [secp, ed] = keys();
+    let secp_sig = sign_hash([3; 32], &secp).unwrap();
+    let ed_sig = sign_hash([3; 32], &ed).unwrap();
+    assert!(matches!(
+        verify_hash([3; 32], &secp_sig, &public_key(&ed)),
+        Err(Error::SchemeMismatch)
+    ));
+    assert!(matches!(
+        verify_hash([3; 32], &ed_sig, &public_key(&secp)),
+        Err(Error::SchemeMismatch)
+    ));
+    assert!(matches!(
+        verify_bytes_unchecked(&[1], &ed_sig, &public_key(&secp)),
+        Err(Error::SchemeMismatch)
+    ));
+}
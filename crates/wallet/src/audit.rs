@@ -0,0 +1,124 @@
+//! A tamper-evident log of every signature the wallet makes.
+//!
+//! Each entry stores the hash of the entry before it and the ids are consecutive,
+//! so changing or deleting an entry breaks the chain from that entry on.
+//! The log lives in the encrypted database next to the keys.
+//!
+//! The chain is not keyed, so anyone who can unlock the wallet can rewrite
+//! the entries, recompute every hash and reset `sqlite_sequence`.
+//! Verifying the log detects accidental or careless changes, not a holder of the password.
+
+use std::{fmt::Display, str::FromStr, time::SystemTime};
+
+use clap::ValueEnum;
+use essential_signer::{HashAlgorithm, Signature};
+use essential_types::Hash;
+
+use crate::{Error, Scheme};
+
+/// The previous hash of the first entry.
+pub(crate) const GENESIS: Hash = [0; 32];
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// The kind of signing operation.
+pub enum SignOperation {
+    /// `Wallet::sign_contract`.
+    Contract,
+    /// `Wallet::sign_postcard` and `Wallet::sign_postcard_with_padding`.
+    Postcard,
+    /// `Wallet::sign_hash`.
+    Hash,
+    /// `Wallet::sign_words`.
+    Words,
+    /// `Wallet::sign_bytes_with_padding`, `Wallet::sign_aligned_bytes`,
+    /// `Wallet::sign_bytes_unchecked` and `Wallet::sign_reader`.
+    Bytes,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A signature recorded in the audit log.
+pub struct AuditEntry {
+    /// The position of the entry in the log starting from 1.
+    pub id: u64,
+    /// When the signature was made or `None` if it was not recorded.
+    pub time: Option<SystemTime>,
+    /// The name the key pair was stored under when it signed.
+    pub name: String,
+    /// The signature scheme of the key.
+    pub scheme: Scheme,
+    /// How the input was signed.
+    pub operation: SignOperation,
+    /// The hash that was signed.
+    /// For contracts this is the content address.
+    pub input_hash: Hash,
+    /// The sha256 hash of the unpadded signature.
+    pub signature_hash: Hash,
+}
+
+/// The fields of an entry as they are stored.
+///
+/// Entries are hashed from the stored values
+/// so any change to a row is detected even if it can't be parsed.
+pub(crate) struct Record<'a> {
+    pub(crate) id: i64,
+    pub(crate) timestamp: i64,
+    pub(crate) name: &'a str,
+    pub(crate) scheme: &'a str,
+    pub(crate) operation: &'a str,
+    pub(crate) input_hash: &'a [u8],
+    pub(crate) signature_hash: &'a [u8],
+}
+
+impl Record<'_> {
+    /// The hash of this entry chained to the hash of the entry before it.
+    pub(crate) fn hash(&self, prev_hash: &[u8]) -> Hash {
+        let mut hasher = HashAlgorithm::Sha256.hasher();
+        hasher.update(prev_hash);
+        hasher.update(&self.id.to_be_bytes());
+        hasher.update(&self.timestamp.to_be_bytes());
+        for field in [
+            self.name.as_bytes(),
+            self.scheme.as_bytes(),
+            self.operation.as_bytes(),
+            self.input_hash,
+            self.signature_hash,
+        ] {
+            // Length prefixes keep the boundaries between fields unambiguous.
+            hasher.update(&(field.len() as u64).to_be_bytes());
+            hasher.update(field);
+        }
+        hasher.finalize()
+    }
+}
+
+/// The hash of a signature as recorded in the log.
+pub(crate) fn signature_hash(sig: &Signature) -> Result<Hash, Error> {
+    Ok(HashAlgorithm::Sha256.hash(&essential_signer::signature_to_bytes(sig)?))
+}
+
+impl Display for SignOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignOperation::Contract => write!(f, "contract"),
+            SignOperation::Postcard => write!(f, "postcard"),
+            SignOperation::Hash => write!(f, "hash"),
+            SignOperation::Words => write!(f, "words"),
+            SignOperation::Bytes => write!(f, "bytes"),
+        }
+    }
+}
+
+impl FromStr for SignOperation {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "contract" => Ok(SignOperation::Contract),
+            "postcard" => Ok(SignOperation::Postcard),
+            "hash" => Ok(SignOperation::Hash),
+            "words" => Ok(SignOperation::Words),
+            "bytes" => Ok(SignOperation::Bytes),
+            _ => Err(Error::UnknownSignOperation(s.to_string())),
+        }
+    }
+}
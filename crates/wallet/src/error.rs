@@ -0,0 +1,121 @@
+use std::path::PathBuf;
+
+use crate::{PolicyViolation, Scheme};
+
+/// Errors that can occur when using the wallet.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// There is no key stored under this name.
+    #[error("Key not found: {0}")]
+    KeyNotFound(String),
+    /// A key is already stored under this name.
+    #[error("A key named {0} already exists")]
+    DuplicateKeyName(String),
+    /// A wallet already exists in the directory.
+    #[error("A wallet already exists at {}", .0.display())]
+    WalletExists(PathBuf),
+    /// There is no wallet in the directory.
+    #[error("No wallet found at {}. Create one with `essential-wallet init`", .0.display())]
+    WalletNotFound(PathBuf),
+    /// The KDF header of the wallet is invalid or unsupported.
+    #[error("Invalid wallet KDF header: {0}")]
+    InvalidKdfHeader(String),
+    /// The KDF parameters are invalid.
+    #[error("Invalid KDF parameters: {0}")]
+    Kdf(#[from] argon2::Error),
+    /// A KDF parameter is larger than the supported maximum.
+    #[error("KDF parameter {0} is {1} but at most {2} is supported")]
+    KdfParamTooLarge(&'static str, u32, u32),
+    /// The wallet was created by a newer version of the wallet.
+    #[error("The wallet schema version {0} is newer than the supported version {1}. Upgrade essential-wallet")]
+    UnsupportedSchemaVersion(i64, i64),
+    /// The password could not unlock the wallet database.
+    #[error("Wrong wallet password")]
+    WrongPassword,
+    /// The scheme of the key does not support this operation.
+    #[error("{0} keys are not supported for {1}")]
+    UnsupportedScheme(Scheme, &'static str),
+    /// The scheme name is not known.
+    #[error("Unknown scheme: {0}")]
+    UnknownScheme(String),
+    /// The signing operation name is not known.
+    #[error("Unknown signing operation: {0}")]
+    UnknownSignOperation(String),
+    /// The stored key is not a valid key for its scheme.
+    #[error("Invalid {1} key stored under {0}")]
+    InvalidStoredKey(String, Scheme),
+    /// The stored metadata of the key pair under this name can't be parsed.
+    #[error("Invalid {1} stored for {0}")]
+    InvalidStoredMetadata(String, &'static str),
+    /// The mnemonic phrase is invalid.
+    #[error("Invalid mnemonic: {0}")]
+    Mnemonic(#[from] bip39::Error),
+    /// The seed does not produce a valid key.
+    #[error("The seed does not produce a valid key")]
+    InvalidSeed,
+    /// The derivation path could not be parsed.
+    #[error("Invalid derivation path: {0}")]
+    InvalidDerivationPath(String),
+    /// The wallet does not have a seed to derive keys from.
+    #[error("The wallet has no seed. Create or restore one first")]
+    NoSeed,
+    /// The wallet already has a seed.
+    #[error("The wallet already has a seed")]
+    SeedExists,
+    /// The scheme of a raw private key can't be detected so it must be given.
+    #[error("The scheme of a raw private key can not be detected. Specify the scheme")]
+    MissingKeyScheme,
+    /// The imported key is not of the expected scheme.
+    #[error("Expected a key for {0} but found a key for {1}")]
+    WrongKeyScheme(Scheme, Scheme),
+    /// The key file holds a key type or curve that is not supported.
+    #[error("Unsupported key type: {0}")]
+    UnsupportedKeyType(String),
+    /// The EC key file does not name the curve of the key.
+    #[error("The EC key does not name its curve")]
+    MissingKeyCurve,
+    /// The PKCS#8 algorithm and the SEC1 parameters of the key name different curves.
+    #[error("The key names two different curves: {0} and {1}")]
+    ConflictingKeyCurves(String, String),
+    /// The encrypted key file uses a KDF or parameters that are not supported.
+    #[error("Unsupported encrypted key KDF: {0}")]
+    UnsupportedKeyKdf(String),
+    /// The key file is not valid PEM or DER.
+    #[error("Invalid key file: {0}")]
+    Der(#[from] pkcs8::der::Error),
+    /// A passphrase is required to export in this format or to import an encrypted key.
+    #[error("A passphrase is required for this key format")]
+    MissingPassphrase,
+    /// Failed to encrypt or decrypt a PKCS#8 key.
+    #[error("PKCS#8 error: {0}")]
+    Pkcs8(#[from] pkcs8::Error),
+    /// The keystore file is invalid or unsupported.
+    #[error("Invalid keystore: {0}")]
+    InvalidKeystore(String),
+    /// The keystore uses scrypt parameters that break RFC 7914,
+    /// which requires n < 2^(16 * r), so they can't be computed.
+    /// Keystores written by old tools with r = 1, like the Web3 Secret Storage
+    /// test vector, must be re-encrypted with other parameters before importing.
+    #[error(
+        "Unsupported keystore scrypt parameters n={0} r={1}. RFC 7914 requires n < 2^(16 * r)"
+    )]
+    UnsupportedScryptParams(u32, u32),
+    /// The keystore MAC does not match.
+    #[error("Keystore MAC mismatch. The passphrase is wrong or the file is corrupted")]
+    KeystoreMacMismatch,
+    /// The signing policy of the key pair does not allow the signature.
+    #[error("The signing policy of {0} does not allow this signature: {1}")]
+    PolicyViolation(String, PolicyViolation),
+    /// An entry of the signing audit log was changed or deleted.
+    #[error("The signing audit log has been tampered with at entry {0}")]
+    AuditLogTampered(u64),
+    /// An error from signing, verifying or encoding data.
+    #[error(transparent)]
+    Signer(#[from] essential_signer::Error),
+    /// An error from the wallet database.
+    #[error(transparent)]
+    Store(#[from] rusqlite::Error),
+    /// Failed to access the wallet directory.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
@@ -0,0 +1,129 @@
+//! Encoding of private keys so they can be exported to other tools.
+
+use clap::ValueEnum;
+use essential_signer::{secp256k1, Key};
+use pkcs8::{
+    der::{asn1::OctetStringRef, pem::LineEnding, Decode, Encode, SecretDocument},
+    pkcs5::{pbes2, scrypt},
+    spki::AlgorithmIdentifierRef,
+    EncryptedPrivateKeyInfo, PrivateKeyInfo,
+};
+use rand::{RngCore, SeedableRng};
+use sec1::{der::pem::PemLabel, EcPrivateKey};
+use zeroize::Zeroizing;
+
+use crate::{
+    import::{EC_PUBLIC_KEY, ED25519, SECP256K1},
+    keystore::{self, KeystoreKdf},
+    Error,
+};
+
+/// Scrypt parameters used for encrypted PKCS#8.
+///
+/// These stay within the default memory limit of OpenSSL.
+const SCRYPT_LOG_N: u8 = 14;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// The format to export a private key in.
+pub enum ExportFormat {
+    /// Unencrypted PKCS#8 PEM.
+    #[default]
+    Pkcs8,
+    /// PKCS#8 PEM encrypted with a passphrase using scrypt and AES-256-CBC.
+    EncryptedPkcs8,
+    /// Ethereum keystore v3 JSON encrypted with a passphrase using scrypt.
+    /// Only secp256k1 keys are supported.
+    KeystoreV3,
+    /// Ethereum keystore v3 JSON encrypted with a passphrase using pbkdf2.
+    /// Only secp256k1 keys are supported.
+    KeystoreV3Pbkdf2,
+}
+
+impl ExportFormat {
+    /// Whether this format is encrypted with a passphrase.
+    pub fn requires_passphrase(&self) -> bool {
+        match self {
+            ExportFormat::Pkcs8 => false,
+            ExportFormat::EncryptedPkcs8
+            | ExportFormat::KeystoreV3
+            | ExportFormat::KeystoreV3Pbkdf2 => true,
+        }
+    }
+}
+
+/// Encode the key in this format.
+///
+/// The passphrase is required for encrypted formats and ignored otherwise.
+pub(crate) fn export_key(
+    key: &Key,
+    format: ExportFormat,
+    passphrase: Option<&str>,
+) -> Result<Zeroizing<String>, Error> {
+    let passphrase = || passphrase.ok_or(Error::MissingPassphrase);
+    match format {
+        ExportFormat::Pkcs8 => {
+            Ok(to_pkcs8(key)?.to_pem(PrivateKeyInfo::PEM_LABEL, LineEnding::LF)?)
+        }
+        ExportFormat::EncryptedPkcs8 => {
+            let mut rng = rand::rngs::StdRng::from_entropy();
+            let mut salt = [0u8; 16];
+            let mut iv = [0u8; 16];
+            rng.fill_bytes(&mut salt);
+            rng.fill_bytes(&mut iv);
+            let params = scrypt::Params::new(SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P, 32)
+                .expect("Scrypt parameters are valid");
+            let params = pbes2::Parameters::scrypt_aes256cbc(params, &salt, &iv)
+                .map_err(pkcs8::Error::from)?;
+            let encrypted = PrivateKeyInfo::from_der(to_pkcs8(key)?.as_bytes())?
+                .encrypt_with_params(params, passphrase()?)?;
+            Ok(encrypted.to_pem(EncryptedPrivateKeyInfo::PEM_LABEL, LineEnding::LF)?)
+        }
+        ExportFormat::KeystoreV3 => Ok(Zeroizing::new(keystore::encrypt(
+            key,
+            passphrase()?,
+            KeystoreKdf::Scrypt,
+        )?)),
+        ExportFormat::KeystoreV3Pbkdf2 => Ok(Zeroizing::new(keystore::encrypt(
+            key,
+            passphrase()?,
+            KeystoreKdf::Pbkdf2,
+        )?)),
+    }
+}
+
+/// Encode the key as PKCS#8 DER.
+///
+/// Secp256k1 keys hold a SEC1 EC key with the public key like OpenSSL writes.
+fn to_pkcs8(key: &Key) -> Result<SecretDocument, Error> {
+    let secret = key.secret_bytes();
+    match key {
+        Key::Secp256k1(k) => {
+            let public_key = k.public_key(secp256k1::SECP256K1).serialize_uncompressed();
+            let ec_key = SecretDocument::encode_msg(&EcPrivateKey {
+                private_key: secret.expose(),
+                parameters: None,
+                public_key: Some(&public_key),
+            })?;
+            let algorithm = AlgorithmIdentifierRef {
+                oid: EC_PUBLIC_KEY,
+                parameters: Some((&SECP256K1).into()),
+            };
+            Ok(SecretDocument::encode_msg(&PrivateKeyInfo::new(
+                algorithm,
+                ec_key.as_bytes(),
+            ))?)
+        }
+        Key::Ed25519(_) => {
+            let ed_key = Zeroizing::new(OctetStringRef::new(secret.expose())?.to_der()?);
+            let algorithm = AlgorithmIdentifierRef {
+                oid: ED25519,
+                parameters: None,
+            };
+            Ok(SecretDocument::encode_msg(&PrivateKeyInfo::new(
+                algorithm, &ed_key,
+            ))?)
+        }
+    }
+}
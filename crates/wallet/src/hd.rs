@@ -0,0 +1,338 @@
+//! Hierarchical deterministic key derivation.
+//!
+//! Secp256k1 keys are derived with BIP32 and ed25519 keys with SLIP-0010.
+//! SLIP-0010 only supports hardened derivation for ed25519.
+
+use std::{fmt::Display, str::FromStr};
+
+use essential_signer::{secp256k1, Key};
+use hmac::{Hmac, Mac};
+use zeroize::Zeroizing;
+
+use crate::{Error, Scheme};
+
+/// Indices at or above this are hardened.
+const HARDENED: u32 = 1 << 31;
+
+/// The coin type used by the default derivation paths.
+///
+/// This is the Ethereum coin type so secp256k1 keys match other wallets.
+/// Essential has no registered SLIP-0044 coin type so ed25519 keys use it too,
+/// which keeps one account layout per seed.
+/// No other wallet is known to derive ed25519 keys at these paths,
+/// so they can only be expected to match keys derived by this wallet.
+/// The keys of the two schemes never collide because SLIP-0010 derives
+/// ed25519 keys from a different master key than BIP32 does for secp256k1.
+const COIN_TYPE: u32 = 60;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+/// A BIP32 derivation path like `m/44'/60'/0'/0/0`.
+///
+/// Hardened indices are written with a `'` or `h` suffix.
+pub struct DerivationPath(Vec<u32>);
+
+struct ExtendedKey {
+    key: Zeroizing<[u8; 32]>,
+    chain_code: Zeroizing<[u8; 32]>,
+}
+
+impl DerivationPath {
+    /// The path of the master key, `m`.
+    pub fn master() -> Self {
+        Self::default()
+    }
+
+    /// The default path for the nth key of this scheme.
+    ///
+    /// This is `m/44'/60'/0'/0/n` for secp256k1
+    /// and `m/44'/60'/0'/0'/n'` for ed25519 which only supports hardened derivation.
+    pub fn default_for(scheme: Scheme, index: u32) -> Self {
+        let account = vec![44 | HARDENED, COIN_TYPE | HARDENED, HARDENED];
+        match scheme {
+            Scheme::Secp256k1 => Self([account, vec![0, index]].concat()),
+            Scheme::Ed25519 => Self([account, vec![HARDENED, index | HARDENED]].concat()),
+        }
+    }
+
+    /// The raw indices of the path.
+    ///
+    /// Hardened indices have the top bit set.
+    pub fn indices(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl Display for DerivationPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "m")?;
+        for index in &self.0 {
+            if index & HARDENED == 0 {
+                write!(f, "/{}", index)?;
+            } else {
+                write!(f, "/{}'", index & !HARDENED)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidDerivationPath(s.to_string());
+        let mut parts = s.trim().split('/');
+        if parts.next() != Some("m") {
+            return Err(invalid());
+        }
+        let indices = parts
+            .map(|part| {
+                let (index, hardened) = match part.strip_suffix(['\'', 'h']) {
+                    Some(index) => (index, HARDENED),
+                    None => (part, 0),
+                };
+                let index: u32 = index.parse().map_err(|_| invalid())?;
+                if index >= HARDENED {
+                    return Err(invalid());
+                }
+                Ok(index | hardened)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self(indices))
+    }
+}
+
+/// Derive the key at this path from the seed.
+pub(crate) fn derive(seed: &[u8], path: &DerivationPath, scheme: Scheme) -> Result<Key, Error> {
+    let mut key = master(seed, scheme)?;
+    for index in path.indices() {
+        key = child(&key, *index, scheme)?;
+    }
+    to_key(&key, scheme)
+}
+
+fn master(seed: &[u8], scheme: Scheme) -> Result<ExtendedKey, Error> {
+    let curve: &[u8] = match scheme {
+        Scheme::Secp256k1 => b"Bitcoin seed",
+        Scheme::Ed25519 => b"ed25519 seed",
+    };
+    let key = split(hmac_sha512(curve, &[seed]));
+    if let Scheme::Secp256k1 = scheme {
+        secp256k1::SecretKey::from_slice(key.key.as_slice()).map_err(|_| Error::InvalidSeed)?;
+    }
+    Ok(key)
+}
+
+fn child(parent: &ExtendedKey, index: u32, scheme: Scheme) -> Result<ExtendedKey, Error> {
+    let hardened = index & HARDENED != 0;
+    let index = index.to_be_bytes();
+    match scheme {
+        Scheme::Secp256k1 => {
+            let parent_key = secp256k1::SecretKey::from_slice(parent.key.as_slice())
+                .map_err(|_| Error::InvalidSeed)?;
+            let i = if hardened {
+                hmac_sha512(&*parent.chain_code, &[&[0], &*parent.key, &index])
+            } else {
+                let secp = secp256k1::Secp256k1::signing_only();
+                let public_key = parent_key.public_key(&secp).serialize();
+                hmac_sha512(&*parent.chain_code, &[&public_key, &index])
+            };
+            let child = split(i);
+            let tweak =
+                secp256k1::Scalar::from_be_bytes(*child.key).map_err(|_| Error::InvalidSeed)?;
+            let key = parent_key
+                .add_tweak(&tweak)
+                .map_err(|_| Error::InvalidSeed)?;
+            Ok(ExtendedKey {
+                key: Zeroizing::new(key.secret_bytes()),
+                chain_code: child.chain_code,
+            })
+        }
+        Scheme::Ed25519 => {
+            if !hardened {
+                return Err(Error::UnsupportedScheme(
+                    Scheme::Ed25519,
+                    "non-hardened derivation",
+                ));
+            }
+            let i = hmac_sha512(&*parent.chain_code, &[&[0], &*parent.key, &index]);
+            Ok(split(i))
+        }
+    }
+}
+
+fn to_key(key: &ExtendedKey, scheme: Scheme) -> Result<Key, Error> {
+    match scheme {
+        Scheme::Secp256k1 => {
+            let key = secp256k1::SecretKey::from_slice(key.key.as_slice())
+                .map_err(|_| Error::InvalidSeed)?;
+            Ok(Key::Secp256k1(key))
+        }
+        Scheme::Ed25519 => Ok(Key::Ed25519(*key.key)),
+    }
+}
+
+fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> Zeroizing<[u8; 64]> {
+    let mut mac =
+        Hmac::<sha2::Sha512>::new_from_slice(key).expect("HMAC can take a key of any size");
+    for data in data {
+        mac.update(data);
+    }
+    Zeroizing::new(mac.finalize().into_bytes().into())
+}
+
+fn split(i: Zeroizing<[u8; 64]>) -> ExtendedKey {
+    let mut key = Zeroizing::new([0; 32]);
+    let mut chain_code = Zeroizing::new([0; 32]);
+    key.copy_from_slice(&i[..32]);
+    chain_code.copy_from_slice(&i[32..]);
+    ExtendedKey { key, chain_code }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The seed of BIP32 and SLIP-0010 test vector 1.
+    const SEED: &str = "000102030405060708090a0b0c0d0e0f";
+
+    fn seed() -> Vec<u8> {
+        hex::decode(SEED).unwrap()
+    }
+
+    fn secret_hex(key: &Key) -> String {
+        hex::encode(key.secret_bytes().expose())
+    }
+
+    /// Check the key and chain code of each step of a test vector chain.
+    fn check_chain(scheme: Scheme, path: &str, expected: &[(&str, &str)]) {
+        let path: DerivationPath = path.parse().unwrap();
+        assert_eq!(path.indices().len() + 1, expected.len());
+        let mut key = master(&seed(), scheme).unwrap();
+        for (i, (private_key, chain_code)) in expected.iter().enumerate() {
+            if i > 0 {
+                key = child(&key, path.indices()[i - 1], scheme).unwrap();
+            }
+            assert_eq!(hex::encode(*key.key), *private_key, "key at depth {}", i);
+            assert_eq!(
+                hex::encode(*key.chain_code),
+                *chain_code,
+                "chain code at depth {}",
+                i
+            );
+        }
+        let derived = derive(&seed(), &path, scheme).unwrap();
+        assert_eq!(secret_hex(&derived), expected[expected.len() - 1].0);
+    }
+
+    #[test]
+    fn bip32_vector_1() {
+        check_chain(
+            Scheme::Secp256k1,
+            "m/0h/1/2h/2/1000000000",
+            &[
+                (
+                    "e8f32e723decf4051aefac8e2c93c9c5b214313817cdb01a1494b917c8436b35",
+                    "873dff81c02f525623fd1fe5167eac3a55a049de3d314bb42ee227ffed37d508",
+                ),
+                (
+                    "edb2e14f9ee77d26dd93b4ecede8d16ed408ce149b6cd80b0715a2d911a0afea",
+                    "47fdacbd0f1097043b78c63c20c34ef4ed9a111d980047ad16282c7ae6236141",
+                ),
+                (
+                    "3c6cb8d0f6a264c91ea8b5030fadaa8e538b020f0a387421a12de9319dc93368",
+                    "2a7857631386ba23dacac34180dd1983734e444fdbf774041578e9b6adb37c19",
+                ),
+                (
+                    "cbce0d719ecf7431d88e6a89fa1483e02e35092af60c042b1df2ff59fa424dca",
+                    "04466b9cc8e161e966409ca52986c584f07e9dc81f735db683c3ff6ec7b1503f",
+                ),
+                (
+                    "0f479245fb19a38a1954c5c7c0ebab2f9bdfd96a17563ef28a6a4b1a2a764ef4",
+                    "cfb71883f01676f587d023cc53a35bc7f88f724b1f8c2892ac1275ac822a3edd",
+                ),
+                (
+                    "471b76e389e528d6de6d816857e012c5455051cad6660850e58372a6c3e6e7c8",
+                    "c783e67b921d2beb8f6b389cc646d7263b4145701dadd2161548a8b078e65e9e",
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn slip10_ed25519_vector_1() {
+        check_chain(
+            Scheme::Ed25519,
+            "m/0'/1'/2'/2'/1000000000'",
+            &[
+                (
+                    "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7",
+                    "90046a93de5380a72b5e45010748567d5ea02bbf6522f979e05c0d8d8ca9fffb",
+                ),
+                (
+                    "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3",
+                    "8b59aa11380b624e81507a27fedda59fea6d0b779a778918a2fd3590e16e9c69",
+                ),
+                (
+                    "b1d0bad404bf35da785a64ca1ac54b2617211d2777696fbffaf208f746ae84f2",
+                    "a320425f77d1b5c2505a6b1b27382b37368ee640e3557c315416801243552f14",
+                ),
+                (
+                    "92a5b23c0b8a99e37d07df3fb9966917f5d06e02ddbd909c7e184371463e9fc9",
+                    "2e69929e00b5ab250f49c3fb1c12f252de4fed2c1db88387094a0f8c4c9ccd6c",
+                ),
+                (
+                    "30d1dc7e5fc04c31219ab25a27ae00b50f6fd66622f6e9c913253d6511d1e662",
+                    "8f6d87f93d750e0efccda017d662a1b31a266e4a6f5993b15f5c1f07f74dd5cc",
+                ),
+                (
+                    "8f94d394a8e8fd6b1bc2f3f49f5c47e385281d5c17e65324b0f62483e37e8793",
+                    "68789923a0cac2cd5a29172a475fe9e0fb14cd6adb5ad98a3fa70333e7afa230",
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn ed25519_requires_hardened() {
+        let path: DerivationPath = "m/0h/1".parse().unwrap();
+        assert!(matches!(
+            derive(&seed(), &path, Scheme::Ed25519),
+            Err(Error::UnsupportedScheme(Scheme::Ed25519, _))
+        ));
+    }
+
+    #[test]
+    fn parse_path() {
+        let path: DerivationPath = "m/0h/1/2'/2/1000000000".parse().unwrap();
+        assert_eq!(
+            path.indices(),
+            [HARDENED, 1, 2 | HARDENED, 2, 1_000_000_000]
+        );
+        assert_eq!(path.to_string(), "m/0'/1/2'/2/1000000000");
+        assert_eq!(path.to_string().parse::<DerivationPath>().unwrap(), path);
+        assert_eq!(
+            "m".parse::<DerivationPath>().unwrap(),
+            DerivationPath::master()
+        );
+        for invalid in ["", "0/1", "m/", "m/x", "m/1''", "m/2147483648", "m/-1"] {
+            assert!(
+                invalid.parse::<DerivationPath>().is_err(),
+                "{} should not parse",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn default_paths() {
+        assert_eq!(
+            DerivationPath::default_for(Scheme::Secp256k1, 3).to_string(),
+            "m/44'/60'/0'/0/3"
+        );
+        assert_eq!(
+            DerivationPath::default_for(Scheme::Ed25519, 3).to_string(),
+            "m/44'/60'/0'/0'/3'"
+        );
+    }
+}
@@ -0,0 +1,308 @@
+//! Parsing of existing private keys so they can be imported.
+//!
+//! Keys can be raw bytes in any `Encoding`, `<scheme>:<data>` strings,
+//! PKCS#8 PEM or DER files, encrypted PKCS#8 PEM or DER files
+//! and SEC1 EC PEM or DER files.
+//! The scheme is detected from the key where the format records it.
+
+use essential_signer::{decode_str, secp256k1, Encoding, Key, SecretBytes};
+use pkcs8::{
+    der::{asn1::OctetStringRef, pem::PemLabel, Decode, SecretDocument},
+    pkcs5::{pbes2, EncryptionScheme},
+    EncryptedPrivateKeyInfo, ObjectIdentifier, PrivateKeyInfo,
+};
+use sec1::EcPrivateKey;
+
+use crate::{keystore, Error, Scheme};
+
+/// The `id-ecPublicKey` algorithm of EC keys.
+pub(crate) const EC_PUBLIC_KEY: ObjectIdentifier =
+    ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+/// The named curve of secp256k1 keys.
+pub(crate) const SECP256K1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.132.0.10");
+/// The `id-Ed25519` algorithm of ed25519 keys.
+pub(crate) const ED25519: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
+
+/// Parse a private key in any of the supported formats.
+///
+/// The encoding is only used for raw keys and `<scheme>:<data>` strings.
+/// The scheme is required for raw keys as it can't be detected.
+/// If it is given for other formats it must match the scheme of the key.
+/// The passphrase is required for encrypted PKCS#8 and ignored otherwise.
+pub(crate) fn parse_key(
+    data: &[u8],
+    encoding: Encoding,
+    scheme: Option<Scheme>,
+    passphrase: Option<&str>,
+) -> Result<Key, Error> {
+    let key = match std::str::from_utf8(data) {
+        Ok(text) => parse_text(text.trim(), encoding, scheme, passphrase)?,
+        Err(_) => parse_der(data, passphrase)?,
+    };
+    let found = Scheme::from(&key);
+    match scheme {
+        Some(expected) if expected != found => Err(Error::WrongKeyScheme(expected, found)),
+        _ => Ok(key),
+    }
+}
+
+fn parse_text(
+    text: &str,
+    encoding: Encoding,
+    scheme: Option<Scheme>,
+    passphrase: Option<&str>,
+) -> Result<Key, Error> {
+    if text.starts_with("-----BEGIN") {
+        return parse_pem(text, passphrase);
+    }
+    if text.contains(':') {
+        return Ok(Key::decode(text, encoding)?);
+    }
+    let bytes = SecretBytes::new(decode_str(text.to_string(), encoding)?);
+    if bytes.expose().len() == 32 {
+        let scheme = scheme.ok_or(Error::MissingKeyScheme)?;
+        return raw_key(bytes.expose(), scheme);
+    }
+    parse_der(bytes.expose(), passphrase)
+}
+
+fn parse_pem(text: &str, passphrase: Option<&str>) -> Result<Key, Error> {
+    let (label, der) = SecretDocument::from_pem(text)?;
+    match label {
+        PrivateKeyInfo::PEM_LABEL => parse_pkcs8(der.as_bytes()),
+        EncryptedPrivateKeyInfo::PEM_LABEL => parse_encrypted_pkcs8(der.as_bytes(), passphrase),
+        EcPrivateKey::PEM_LABEL => parse_sec1(der.as_bytes()),
+        _ => Err(Error::UnsupportedKeyType(label.to_string())),
+    }
+}
+
+/// DER files don't record their type so try PKCS#8, encrypted PKCS#8 then SEC1.
+fn parse_der(der: &[u8], passphrase: Option<&str>) -> Result<Key, Error> {
+    match PrivateKeyInfo::from_der(der) {
+        Ok(_) => parse_pkcs8(der),
+        Err(e) => match (
+            EncryptedPrivateKeyInfo::from_der(der),
+            EcPrivateKey::from_der(der),
+        ) {
+            (Ok(_), _) => parse_encrypted_pkcs8(der, passphrase),
+            (_, Ok(_)) => parse_sec1(der),
+            _ => Err(e.into()),
+        },
+    }
+}
+
+fn parse_pkcs8(der: &[u8]) -> Result<Key, Error> {
+    let info = PrivateKeyInfo::from_der(der)?;
+    match info.algorithm.oid {
+        EC_PUBLIC_KEY => {
+            let curve = info.algorithm.parameters_oid().ok();
+            let key = EcPrivateKey::from_der(info.private_key)?;
+            ec_key(&key, curve)
+        }
+        ED25519 => {
+            let key = OctetStringRef::from_der(info.private_key)?;
+            raw_key(key.as_bytes(), Scheme::Ed25519)
+        }
+        oid => Err(Error::UnsupportedKeyType(oid.to_string())),
+    }
+}
+
+/// The KDF parameters come from the file so they are held to the keystore limits
+/// before anything is derived.
+fn parse_encrypted_pkcs8(der: &[u8], passphrase: Option<&str>) -> Result<Key, Error> {
+    let info = EncryptedPrivateKeyInfo::from_der(der)?;
+    let EncryptionScheme::Pbes2(params) = &info.encryption_algorithm else {
+        return Err(Error::UnsupportedKeyKdf("PBES1".to_string()));
+    };
+    match &params.kdf {
+        pbes2::Kdf::Scrypt(scrypt) => {
+            if !keystore::scrypt_within_limits(
+                scrypt.cost_parameter,
+                scrypt.block_size.into(),
+                scrypt.parallelization.into(),
+            ) {
+                return Err(Error::UnsupportedKeyKdf(format!(
+                    "scrypt n={} r={} p={}",
+                    scrypt.cost_parameter, scrypt.block_size, scrypt.parallelization
+                )));
+            }
+        }
+        pbes2::Kdf::Pbkdf2(pbkdf2) => {
+            if !keystore::pbkdf2_within_limits(pbkdf2.iteration_count) {
+                return Err(Error::UnsupportedKeyKdf(format!(
+                    "pbkdf2 c={}",
+                    pbkdf2.iteration_count
+                )));
+            }
+        }
+        kdf => return Err(Error::UnsupportedKeyKdf(kdf.oid().to_string())),
+    }
+    let der = info.decrypt(passphrase.ok_or(Error::MissingPassphrase)?)?;
+    parse_pkcs8(der.as_bytes())
+}
+
+fn parse_sec1(der: &[u8]) -> Result<Key, Error> {
+    let key = EcPrivateKey::from_der(der)?;
+    ec_key(&key, None)
+}
+
+/// The curve may be given by the PKCS#8 algorithm, the SEC1 parameters or both.
+/// Only secp256k1 is supported and the key must name it.
+fn ec_key(key: &EcPrivateKey, curve: Option<ObjectIdentifier>) -> Result<Key, Error> {
+    let params = key.parameters.and_then(|p| p.named_curve());
+    let curve = match (curve, params) {
+        (Some(curve), Some(params)) if curve != params => {
+            return Err(Error::ConflictingKeyCurves(
+                curve.to_string(),
+                params.to_string(),
+            ))
+        }
+        (curve, params) => curve.or(params).ok_or(Error::MissingKeyCurve)?,
+    };
+    match curve {
+        SECP256K1 => raw_key(key.private_key, Scheme::Secp256k1),
+        oid => Err(Error::UnsupportedKeyType(oid.to_string())),
+    }
+}
+
+fn raw_key(bytes: &[u8], scheme: Scheme) -> Result<Key, Error> {
+    match scheme {
+        Scheme::Secp256k1 => {
+            let key =
+                secp256k1::SecretKey::from_slice(bytes).map_err(essential_signer::Error::from)?;
+            Ok(Key::Secp256k1(key))
+        }
+        Scheme::Ed25519 => {
+            let key = bytes
+                .try_into()
+                .map_err(|_| essential_signer::Error::InvalidPrivateKeyLength(bytes.len()))?;
+            Ok(Key::Ed25519(key))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pkcs8::{
+        der::{pem::LineEnding, Encode},
+        pkcs5::scrypt,
+        spki::AlgorithmIdentifierRef,
+    };
+    use sec1::EcParameters;
+
+    use super::*;
+    use crate::export::{export_key, ExportFormat};
+
+    /// The OID of the NIST P-256 curve.
+    const P256: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.3.1.7");
+
+    fn secret_hex(key: &Key) -> String {
+        hex::encode(key.secret_bytes().expose())
+    }
+
+    fn sec1(curve: Option<ObjectIdentifier>) -> Vec<u8> {
+        EcPrivateKey {
+            private_key: &[1; 32],
+            parameters: curve.map(EcParameters::NamedCurve),
+            public_key: None,
+        }
+        .to_der()
+        .unwrap()
+    }
+
+    fn pkcs8(curve: ObjectIdentifier, sec1: &[u8]) -> Vec<u8> {
+        let algorithm = AlgorithmIdentifierRef {
+            oid: EC_PUBLIC_KEY,
+            parameters: Some((&curve).into()),
+        };
+        PrivateKeyInfo::new(algorithm, sec1).to_der().unwrap()
+    }
+
+    fn parse(data: &[u8]) -> Result<Key, Error> {
+        parse_key(data, Encoding::Hex, None, None)
+    }
+
+    /// Short DER can be valid UTF-8 which would be parsed as text so use PEM.
+    fn parse_sec1_pem(der: Vec<u8>) -> Result<Key, Error> {
+        let pem = SecretDocument::try_from(der)
+            .unwrap()
+            .to_pem(EcPrivateKey::PEM_LABEL, LineEnding::LF)
+            .unwrap();
+        parse(pem.as_bytes())
+    }
+
+    #[test]
+    fn sec1_curves() {
+        let key = parse(&sec1(Some(SECP256K1))).unwrap();
+        assert_eq!(secret_hex(&key), "01".repeat(32));
+        let key = parse_sec1_pem(sec1(Some(SECP256K1))).unwrap();
+        assert_eq!(secret_hex(&key), "01".repeat(32));
+
+        assert!(matches!(
+            parse_sec1_pem(sec1(None)),
+            Err(Error::MissingKeyCurve)
+        ));
+        assert!(matches!(
+            parse_sec1_pem(sec1(Some(P256))),
+            Err(Error::UnsupportedKeyType(oid)) if oid == P256.to_string()
+        ));
+    }
+
+    #[test]
+    fn pkcs8_curves() {
+        for params in [None, Some(SECP256K1)] {
+            let key = parse(&pkcs8(SECP256K1, &sec1(params))).unwrap();
+            assert_eq!(secret_hex(&key), "01".repeat(32));
+        }
+        assert!(matches!(
+            parse(&pkcs8(SECP256K1, &sec1(Some(P256)))),
+            Err(Error::ConflictingKeyCurves(..))
+        ));
+        assert!(matches!(
+            parse(&pkcs8(P256, &sec1(Some(SECP256K1)))),
+            Err(Error::ConflictingKeyCurves(..))
+        ));
+        assert!(matches!(
+            parse(&pkcs8(P256, &sec1(None))),
+            Err(Error::UnsupportedKeyType(_))
+        ));
+    }
+
+    #[test]
+    fn encrypted_pkcs8() {
+        let key = Key::Ed25519([3; 32]);
+        let pem = export_key(&key, ExportFormat::EncryptedPkcs8, Some("passphrase")).unwrap();
+        let (_, der) = SecretDocument::from_pem(&pem).unwrap();
+        for data in [pem.as_bytes(), der.as_bytes()] {
+            let imported = parse_key(data, Encoding::Hex, None, Some("passphrase")).unwrap();
+            assert_eq!(secret_hex(&imported), secret_hex(&key));
+            assert!(matches!(parse(data), Err(Error::MissingPassphrase)));
+        }
+        assert!(matches!(
+            parse_key(pem.as_bytes(), Encoding::Hex, None, Some("wrong")),
+            Err(Error::Pkcs8(_))
+        ));
+    }
+
+    #[test]
+    fn encrypted_pkcs8_rejects_expensive_kdfs() {
+        let encrypted = |params| {
+            EncryptedPrivateKeyInfo {
+                encryption_algorithm: EncryptionScheme::Pbes2(params),
+                encrypted_data: &[0; 48],
+            }
+            .to_der()
+            .unwrap()
+        };
+        let scrypt = scrypt::Params::new(21, 8, 1, 32).unwrap();
+        let scrypt = pbes2::Parameters::scrypt_aes256cbc(scrypt, &[0; 16], &[0; 16]).unwrap();
+        let pbkdf2 =
+            pbes2::Parameters::pbkdf2_sha256_aes256cbc(100_000_000, &[0; 16], &[0; 16]).unwrap();
+        for params in [scrypt, pbkdf2] {
+            assert!(matches!(
+                parse_key(&encrypted(params), Encoding::Hex, None, Some("passphrase")),
+                Err(Error::UnsupportedKeyKdf(_))
+            ));
+        }
+    }
+}
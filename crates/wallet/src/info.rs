@@ -0,0 +1,61 @@
+//! Metadata about stored key pairs.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use essential_signer::PublicKey;
+
+use crate::{DerivationPath, Scheme, SigningPolicy};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Information about a stored key pair. Does not contain the secret key.
+///
+/// Times are `None` for keys stored before they were recorded.
+pub struct KeyInfo {
+    /// The name the key pair is stored under.
+    pub name: String,
+    /// The signature scheme of the key.
+    pub scheme: Scheme,
+    /// The path the key was derived at if it was derived from the wallet's master seed.
+    pub derivation_path: Option<DerivationPath>,
+    /// When the key pair was stored.
+    pub created_at: Option<SystemTime>,
+    /// When the key pair was last renamed or had its description, tags or policy changed.
+    pub updated_at: Option<SystemTime>,
+    /// When the key pair was last used to sign.
+    pub last_used_at: Option<SystemTime>,
+    /// A free-form description of the key pair.
+    pub description: Option<String>,
+    /// Tags for grouping key pairs.
+    pub tags: Vec<String>,
+    /// Restrictions on how the key pair may sign.
+    pub policy: SigningPolicy,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A named public key stored without a secret key,
+/// like the key of a teammate or service, for checking their signatures.
+pub struct WatchOnlyKey {
+    /// The name the public key is stored under.
+    pub name: String,
+    /// The public key.
+    pub public_key: PublicKey,
+    /// When the public key was stored or `None` if it was not recorded.
+    pub created_at: Option<SystemTime>,
+}
+
+/// The current time as seconds since the unix epoch, as stored in the database.
+pub(crate) fn now() -> i64 {
+    to_unix(SystemTime::now())
+}
+
+/// Convert a time to seconds since the unix epoch to store in the database.
+pub(crate) fn to_unix(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Convert seconds since the unix epoch from the database.
+pub(crate) fn from_unix(secs: Option<i64>) -> Option<SystemTime> {
+    secs.map(|s| UNIX_EPOCH + Duration::from_secs(s.max(0) as u64))
+}
@@ -0,0 +1,170 @@
+//! Derivation of the database key from the wallet password.
+//!
+//! The salt and Argon2id parameters are stored unencrypted in a header
+//! file next to the database so the key can be derived before it is opened.
+//! The header is replaced by writing a pending header, rekeying the database
+//! and then renaming the pending header over the old one.
+//! If this is interrupted the pending header is used when the old one fails.
+
+use std::path::Path;
+
+use argon2::{Algorithm, Argon2, Version};
+use rand::{RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::Error;
+
+const HEADER_FILE: &str = "accounts.kdf.json";
+const PENDING_FILE: &str = "accounts.kdf.json.new";
+const VERSION: u32 = 1;
+const ARGON2ID: &str = "argon2id";
+const SALT_LEN: usize = 16;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+/// Argon2id parameters used to derive the database key from the password.
+pub struct KdfParams {
+    /// Memory cost in KiB.
+    pub memory_kib: u32,
+    /// Number of iterations.
+    pub iterations: u32,
+    /// Degree of parallelism.
+    pub parallelism: u32,
+}
+
+impl KdfParams {
+    /// The largest supported memory cost of 1 GiB.
+    ///
+    /// The header is not authenticated so a larger cost could be used
+    /// to make opening the wallet exhaust memory.
+    pub const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+    /// The largest supported number of iterations.
+    pub const MAX_ITERATIONS: u32 = 64;
+    /// The largest supported degree of parallelism.
+    pub const MAX_PARALLELISM: u32 = 16;
+
+    /// Check the parameters are within the supported limits.
+    fn check(&self) -> Result<(), Error> {
+        for (name, value, max) in [
+            ("memory_kib", self.memory_kib, Self::MAX_MEMORY_KIB),
+            ("iterations", self.iterations, Self::MAX_ITERATIONS),
+            ("parallelism", self.parallelism, Self::MAX_PARALLELISM),
+        ] {
+            if value > max {
+                return Err(Error::KdfParamTooLarge(name, value, max));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for KdfParams {
+    /// The OWASP recommended minimum of 19 MiB and 2 iterations.
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Header {
+    version: u32,
+    kdf: String,
+    #[serde(with = "hex")]
+    salt: Vec<u8>,
+    #[serde(flatten)]
+    params: KdfParams,
+}
+
+impl Header {
+    /// A header with a new random salt.
+    pub(crate) fn new(params: KdfParams) -> Self {
+        let mut salt = vec![0u8; SALT_LEN];
+        rand::rngs::StdRng::from_entropy().fill_bytes(&mut salt);
+        Self {
+            version: VERSION,
+            kdf: ARGON2ID.to_string(),
+            salt,
+            params,
+        }
+    }
+
+    pub(crate) fn params(&self) -> KdfParams {
+        self.params
+    }
+
+    /// Read the header of the wallet in this directory.
+    ///
+    /// Wallets created before the header was added don't have one.
+    pub(crate) fn read(dir: &Path) -> Result<Option<Self>, Error> {
+        read(&dir.join(HEADER_FILE))
+    }
+
+    /// Read a pending header left by an interrupted rekey.
+    pub(crate) fn read_pending(dir: &Path) -> Result<Option<Self>, Error> {
+        read(&dir.join(PENDING_FILE))
+    }
+
+    /// Write this header as the pending header.
+    pub(crate) fn write_pending(&self, dir: &Path) -> Result<(), Error> {
+        let json = serde_json::to_vec_pretty(self).expect("Header serializes to JSON");
+        std::fs::write(dir.join(PENDING_FILE), json)?;
+        Ok(())
+    }
+
+    /// Replace the header with the pending header.
+    pub(crate) fn commit_pending(dir: &Path) -> Result<(), Error> {
+        std::fs::rename(dir.join(PENDING_FILE), dir.join(HEADER_FILE))?;
+        Ok(())
+    }
+
+    /// Remove the pending header if there is one.
+    pub(crate) fn discard_pending(dir: &Path) -> Result<(), Error> {
+        match std::fs::remove_file(dir.join(PENDING_FILE)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Derive the raw database key from the password.
+    pub(crate) fn derive_key(&self, password: &str) -> Result<Zeroizing<[u8; 32]>, Error> {
+        self.params.check()?;
+        let params = argon2::Params::new(
+            self.params.memory_kib,
+            self.params.iterations,
+            self.params.parallelism,
+            Some(32),
+        )?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params).hash_password_into(
+            password.as_bytes(),
+            &self.salt,
+            key.as_mut(),
+        )?;
+        Ok(key)
+    }
+}
+
+fn read(path: &Path) -> Result<Option<Header>, Error> {
+    let json = match std::fs::read(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let header: Header =
+        serde_json::from_slice(&json).map_err(|e| Error::InvalidKdfHeader(e.to_string()))?;
+    if header.version != VERSION || header.kdf != ARGON2ID {
+        return Err(Error::InvalidKdfHeader(format!(
+            "unsupported {} version {}",
+            header.kdf, header.version
+        )));
+    }
+    header
+        .params
+        .check()
+        .map_err(|e| Error::InvalidKdfHeader(e.to_string()))?;
+    Ok(Some(header))
+}
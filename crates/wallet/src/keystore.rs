@@ -0,0 +1,406 @@
+//! Ethereum keystore v3 files, also known as Web3 Secret Storage.
+//!
+//! The key is encrypted with AES-128-CTR using a key derived from the passphrase
+//! with either scrypt or pbkdf2.
+//! The MAC is checked in constant time before a key is decrypted.
+//! Only secp256k1 keys are supported.
+//!
+//! Scrypt keystores with n >= 2^(16 * r) are not supported because
+//! RFC 7914 forbids them and the scrypt implementation rejects them.
+//! This includes the scrypt test vector of the Web3 Secret Storage spec which uses r = 1.
+//! Keystores written by geth and most other tools use r = 8 and are supported.
+
+use aes::cipher::{KeyIvInit, StreamCipher};
+use essential_signer::{secp256k1, Key};
+use rand::{RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroizing;
+
+use crate::{Error, Scheme};
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+const VERSION: u32 = 3;
+const CIPHER: &str = "aes-128-ctr";
+const DKLEN: usize = 32;
+
+/// Scrypt parameters used when encrypting.
+///
+/// These are the "light" parameters from geth which keep
+/// unlocking fast while still being accepted by other tools.
+const SCRYPT_LOG_N: u8 = 12;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 6;
+
+/// Pbkdf2 iterations used when encrypting.
+const PBKDF2_C: u32 = 262_144;
+const PBKDF2_PRF: &str = "hmac-sha256";
+
+/// Limits on the parameters of keystores being decrypted
+/// so a malicious file can't make the wallet allocate or spin forever.
+///
+/// These are well above the "standard" geth parameters
+/// of n = 2^18, r = 8, p = 1 and c = 262144.
+const MAX_DKLEN: usize = 64;
+const MAX_SCRYPT_N: u32 = 1 << 20;
+const MAX_SCRYPT_P: u32 = 16;
+/// The largest n * r * p which bounds the time taken by scrypt.
+const MAX_SCRYPT_COST: u64 = 1 << 24;
+/// The most memory scrypt may use, 128 * n * r bytes.
+/// This is twice what the "standard" geth parameters use.
+const MAX_SCRYPT_MEMORY: u64 = 512 << 20;
+const MAX_PBKDF2_C: u32 = 10_000_000;
+
+#[derive(Clone, Copy, Debug)]
+/// The key derivation function used to encrypt a keystore.
+pub(crate) enum KeystoreKdf {
+    Scrypt,
+    Pbkdf2,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Keystore {
+    version: u32,
+    id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    address: Option<String>,
+    crypto: Crypto,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Crypto {
+    cipher: String,
+    cipherparams: CipherParams,
+    #[serde(with = "hex")]
+    ciphertext: Vec<u8>,
+    #[serde(flatten)]
+    kdf: Kdf,
+    #[serde(with = "hex")]
+    mac: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CipherParams {
+    #[serde(with = "hex")]
+    iv: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kdf", content = "kdfparams", rename_all = "lowercase")]
+enum Kdf {
+    Scrypt {
+        dklen: usize,
+        n: u32,
+        r: u32,
+        p: u32,
+        #[serde(with = "hex")]
+        salt: Vec<u8>,
+    },
+    Pbkdf2 {
+        dklen: usize,
+        c: u32,
+        prf: String,
+        #[serde(with = "hex")]
+        salt: Vec<u8>,
+    },
+}
+
+/// Encrypt a secp256k1 key as keystore v3 JSON.
+pub(crate) fn encrypt(key: &Key, passphrase: &str, kdf: KeystoreKdf) -> Result<String, Error> {
+    let Key::Secp256k1(secret_key) = key else {
+        return Err(Error::UnsupportedScheme(Scheme::Ed25519, "keystore v3"));
+    };
+    let mut rng = rand::rngs::StdRng::from_entropy();
+    let mut salt = vec![0u8; 32];
+    let mut iv = vec![0u8; 16];
+    let mut id = [0u8; 16];
+    rng.fill_bytes(&mut salt);
+    rng.fill_bytes(&mut iv);
+    rng.fill_bytes(&mut id);
+
+    let kdf = match kdf {
+        KeystoreKdf::Scrypt => Kdf::Scrypt {
+            dklen: DKLEN,
+            n: 1 << SCRYPT_LOG_N,
+            r: SCRYPT_R,
+            p: SCRYPT_P,
+            salt,
+        },
+        KeystoreKdf::Pbkdf2 => Kdf::Pbkdf2 {
+            dklen: DKLEN,
+            c: PBKDF2_C,
+            prf: PBKDF2_PRF.to_string(),
+            salt,
+        },
+    };
+    let derived = derive_key(&kdf, passphrase)?;
+
+    let mut ciphertext = key.secret_bytes().expose().to_vec();
+    Aes128Ctr::new(derived[..16].into(), iv.as_slice().into()).apply_keystream(&mut ciphertext);
+    let mac = mac(&derived, &ciphertext);
+
+    let keystore = Keystore {
+        version: VERSION,
+        id: uuid_v4(id),
+        address: Some(address(&secret_key.public_key(secp256k1::SECP256K1))),
+        crypto: Crypto {
+            cipher: CIPHER.to_string(),
+            cipherparams: CipherParams { iv },
+            ciphertext,
+            kdf,
+            mac: mac.to_vec(),
+        },
+    };
+    Ok(serde_json::to_string_pretty(&keystore).expect("Keystore serializes to JSON"))
+}
+
+/// Decrypt a secp256k1 key from keystore v3 JSON.
+///
+/// Returns an error if the MAC does not match which
+/// means the passphrase is wrong or the file is corrupted.
+pub(crate) fn decrypt(json: &str, passphrase: &str) -> Result<Key, Error> {
+    let keystore: Keystore =
+        serde_json::from_str(json).map_err(|e| Error::InvalidKeystore(e.to_string()))?;
+    if keystore.version != VERSION {
+        return Err(Error::InvalidKeystore(format!(
+            "version {}",
+            keystore.version
+        )));
+    }
+    let crypto = keystore.crypto;
+    if crypto.cipher != CIPHER {
+        return Err(Error::InvalidKeystore(format!("cipher {}", crypto.cipher)));
+    }
+    if crypto.cipherparams.iv.len() != 16 {
+        return Err(Error::InvalidKeystore("iv length".to_string()));
+    }
+
+    let derived = derive_key(&crypto.kdf, passphrase)?;
+    if !bool::from(mac(&derived, &crypto.ciphertext).ct_eq(crypto.mac.as_slice())) {
+        return Err(Error::KeystoreMacMismatch);
+    }
+
+    let mut secret = Zeroizing::new(crypto.ciphertext);
+    Aes128Ctr::new(
+        derived[..16].into(),
+        crypto.cipherparams.iv.as_slice().into(),
+    )
+    .apply_keystream(&mut secret);
+    let secret_key = secp256k1::SecretKey::from_slice(&secret)
+        .map_err(|_| Error::InvalidKeystore("private key".to_string()))?;
+
+    if let Some(expected) = keystore.address {
+        let expected = expected.trim_start_matches("0x").to_lowercase();
+        if expected != address(&secret_key.public_key(secp256k1::SECP256K1)) {
+            return Err(Error::InvalidKeystore(
+                "address does not match the key".to_string(),
+            ));
+        }
+    }
+    Ok(Key::Secp256k1(secret_key))
+}
+
+fn derive_key(kdf: &Kdf, passphrase: &str) -> Result<Zeroizing<Vec<u8>>, Error> {
+    match kdf {
+        Kdf::Scrypt {
+            dklen,
+            n,
+            r,
+            p,
+            salt,
+        } => {
+            let invalid =
+                || Error::InvalidKeystore(format!("scrypt parameters n={} r={} p={}", n, r, p));
+            if !(DKLEN..=MAX_DKLEN).contains(dklen)
+                || !n.is_power_of_two()
+                || !scrypt_within_limits(u64::from(*n), *r, *p)
+            {
+                return Err(invalid());
+            }
+            let log_n = n.ilog2();
+            if log_n >= r * 16 {
+                return Err(Error::UnsupportedScryptParams(*n, *r));
+            }
+            let params = scrypt::Params::new(log_n as u8, *r, *p, *dklen).map_err(|_| invalid())?;
+            let mut derived = Zeroizing::new(vec![0u8; *dklen]);
+            scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut derived)
+                .map_err(|_| invalid())?;
+            Ok(derived)
+        }
+        Kdf::Pbkdf2 {
+            dklen,
+            c,
+            prf,
+            salt,
+        } => {
+            if prf != PBKDF2_PRF {
+                return Err(Error::InvalidKeystore(format!("prf {}", prf)));
+            }
+            if !(DKLEN..=MAX_DKLEN).contains(dklen) || !pbkdf2_within_limits(*c) {
+                return Err(Error::InvalidKeystore(format!(
+                    "pbkdf2 parameters c={} dklen={}",
+                    c, dklen
+                )));
+            }
+            let mut derived = Zeroizing::new(vec![0u8; *dklen]);
+            pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase.as_bytes(), salt, *c, &mut derived);
+            Ok(derived)
+        }
+    }
+}
+
+/// Whether scrypt with these parameters stays within the time and memory limits.
+///
+/// Encrypted PKCS#8 keys are held to the same limits.
+pub(crate) fn scrypt_within_limits(n: u64, r: u32, p: u32) -> bool {
+    n <= u64::from(MAX_SCRYPT_N)
+        && r > 0
+        && (1..=MAX_SCRYPT_P).contains(&p)
+        && n * u64::from(r) * u64::from(p) <= MAX_SCRYPT_COST
+        && 128 * n * u64::from(r) <= MAX_SCRYPT_MEMORY
+}
+
+/// Whether pbkdf2 with this many iterations stays within the time limit.
+pub(crate) fn pbkdf2_within_limits(c: u32) -> bool {
+    (1..=MAX_PBKDF2_C).contains(&c)
+}
+
+/// The MAC is the keccak256 hash of the second half of the derived key and the ciphertext.
+fn mac(derived: &[u8], ciphertext: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(&derived[16..32]);
+    hasher.update(ciphertext);
+    hasher.finalize().into()
+}
+
+/// The Ethereum address of the public key without the `0x` prefix.
+fn address(public_key: &secp256k1::PublicKey) -> String {
+    let hash = Keccak256::digest(&public_key.serialize_uncompressed()[1..]);
+    hex::encode(&hash[12..])
+}
+
+/// Format random bytes as a version 4 UUID.
+fn uuid_v4(mut bytes: [u8; 16]) -> String {
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The private key of the Web3 Secret Storage test vectors.
+    const SPEC_KEY: &str = "7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d";
+
+    const SPEC_PBKDF2: &str = r#"{
+        "crypto": {
+            "cipher": "aes-128-ctr",
+            "cipherparams": { "iv": "6087dab2f9fdbbfaddc31a909735c1e6" },
+            "ciphertext": "5318b4d5bcd28de64ee5559e671353e16f075ecae9f99c7a79a38af5f869aa46",
+            "kdf": "pbkdf2",
+            "kdfparams": {
+                "c": 262144,
+                "dklen": 32,
+                "prf": "hmac-sha256",
+                "salt": "ae3cd4e7013836a3df6bd7241b12db061dbe2c6785853cce422d148a624ce0bd"
+            },
+            "mac": "517ead924a9d0dc3124507e3393d175ce3ff7c1e96529c6c555ce9e51205e9b2"
+        },
+        "id": "3198bc9c-6672-5ab3-d995-4942343ae5b6",
+        "version": 3
+    }"#;
+
+    const SPEC_SCRYPT: &str = r#"{
+        "crypto": {
+            "cipher": "aes-128-ctr",
+            "cipherparams": { "iv": "83dbcc02d8ccb40e466191a123791e0e" },
+            "ciphertext": "d172bf743a674da9cdad04534d56926ef8358534d458fffccd4e6ad2fbde479c",
+            "kdf": "scrypt",
+            "kdfparams": {
+                "dklen": 32,
+                "n": 262144,
+                "r": 1,
+                "p": 8,
+                "salt": "ab0c7876052600dd703518d6fc3fe8984592145b591fc8fb5c6d43190334ba19"
+            },
+            "mac": "2103ac29920d71da29f15d75b4a16dbe95cfd7ff8faea1056c33131d846e3097"
+        },
+        "id": "3198bc9c-6672-5ab3-d995-4942343ae5b6",
+        "version": 3
+    }"#;
+
+    fn secret_hex(key: &Key) -> String {
+        hex::encode(key.secret_bytes().expose())
+    }
+
+    /// Replace a kdf parameter of a keystore.
+    fn with_param(json: &str, param: &str, value: u64) -> String {
+        let mut keystore: serde_json::Value = serde_json::from_str(json).unwrap();
+        keystore["crypto"]["kdfparams"][param] = value.into();
+        keystore.to_string()
+    }
+
+    #[test]
+    fn spec_pbkdf2() {
+        let key = decrypt(SPEC_PBKDF2, "testpassword").unwrap();
+        assert_eq!(secret_hex(&key), SPEC_KEY);
+    }
+
+    /// The spec vector uses n = 2^18 with r = 1 which RFC 7914 does not allow.
+    #[test]
+    fn spec_scrypt_is_unsupported() {
+        assert!(matches!(
+            decrypt(SPEC_SCRYPT, "testpassword"),
+            Err(Error::UnsupportedScryptParams(262144, 1))
+        ));
+    }
+
+    #[test]
+    fn round_trip() {
+        let key = Key::Secp256k1(secp256k1::SecretKey::from_slice(&[7; 32]).unwrap());
+        for kdf in [KeystoreKdf::Scrypt, KeystoreKdf::Pbkdf2] {
+            let json = encrypt(&key, "passphrase", kdf).unwrap();
+            let decrypted = decrypt(&json, "passphrase").unwrap();
+            assert_eq!(secret_hex(&decrypted), secret_hex(&key));
+        }
+        let json = encrypt(&key, "passphrase", KeystoreKdf::Pbkdf2).unwrap();
+        assert!(matches!(
+            decrypt(&json, "wrong"),
+            Err(Error::KeystoreMacMismatch)
+        ));
+    }
+
+    #[test]
+    fn rejects_expensive_params() {
+        let invalid = |json: String| {
+            assert!(matches!(
+                decrypt(&json, "testpassword"),
+                Err(Error::InvalidKeystore(_))
+            ));
+        };
+        invalid(with_param(SPEC_PBKDF2, "dklen", 31));
+        invalid(with_param(SPEC_PBKDF2, "dklen", 1 << 40));
+        invalid(with_param(SPEC_PBKDF2, "c", 0));
+        invalid(with_param(SPEC_PBKDF2, "c", u32::MAX.into()));
+        invalid(with_param(SPEC_SCRYPT, "dklen", 65));
+        invalid(with_param(SPEC_SCRYPT, "n", 1 << 31));
+        invalid(with_param(SPEC_SCRYPT, "n", 3));
+        invalid(with_param(SPEC_SCRYPT, "p", 0));
+        invalid(with_param(SPEC_SCRYPT, "p", u32::MAX.into()));
+        invalid(with_param(SPEC_SCRYPT, "r", u32::MAX.into()));
+        // Within the time limit but 1 GiB of memory.
+        let scrypt = with_param(&with_param(SPEC_SCRYPT, "r", 8), "p", 1);
+        invalid(with_param(&scrypt, "n", 1 << 20));
+    }
+}
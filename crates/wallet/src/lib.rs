@@ -12,27 +12,52 @@
 #![deny(missing_docs)]
 #![deny(unsafe_code)]
 
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::{fmt::Display, str::FromStr};
 
 use clap::ValueEnum;
 use essential_signer::Key;
 use essential_signer::PublicKey;
+use essential_signer::SecretBytes;
 use essential_types::contract::Contract;
 use essential_types::{Hash, Word};
-use rand::SeedableRng;
+use rand::{RngCore, SeedableRng};
 use serde::Serialize;
 
+pub use audit::{AuditEntry, SignOperation};
+pub use error::Error;
 pub use essential_signer::ed25519_dalek;
 pub use essential_signer::secp256k1;
+pub use essential_signer::Encoding;
+pub use essential_signer::HashAlgorithm;
 pub use essential_signer::Padding;
 pub use essential_signer::Signature;
-
+pub use export::ExportFormat;
+pub use hd::DerivationPath;
+pub use info::{KeyInfo, WatchOnlyKey};
+pub use kdf::KdfParams;
+pub use mnemonic::MnemonicLength;
+pub use policy::{Confirmation, PolicyViolation, SigningPolicy};
+pub use zeroize::Zeroizing;
+
+mod audit;
+mod error;
+mod export;
+mod hd;
+mod import;
+mod info;
+mod kdf;
+mod keystore;
+mod mnemonic;
+mod policy;
 mod store;
 
 const NAME: &str = "essential-wallet";
+const DB_FILE: &str = "accounts.sqlite3";
 
-#[derive(ValueEnum, Clone, Copy, Debug)]
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
 /// Which signature scheme to use.
 pub enum Scheme {
     /// The secp256k1 signature scheme.
@@ -46,106 +71,524 @@ pub enum Scheme {
 /// Stores secret keys in sqlcipher database.
 pub struct Wallet {
     store: store::Store,
+    path: PathBuf,
+    confirmation: Option<Confirmation>,
     #[cfg(feature = "test-utils")]
     dir: Option<tempfile::TempDir>,
 }
 
 impl Wallet {
-    /// Create a new wallet with a password and directory.
-    pub fn new(password: &str, path: PathBuf) -> anyhow::Result<Self> {
-        let mut path = db_dir(Some(path.clone()))?;
-        path.push("accounts.sqlite3");
-        let store = store::Store::new(password, &path)?;
+    /// Create a new wallet with a password in this directory.
+    ///
+    /// The database key is derived from the password with the default `KdfParams`.
+    /// Returns an error if the directory already has a wallet.
+    pub fn create(password: &str, dir: PathBuf) -> Result<Self, Error> {
+        Self::create_with_kdf(password, dir, KdfParams::default())
+    }
 
-        #[cfg(not(feature = "test-utils"))]
-        let r = Ok(Self { store });
-        #[cfg(feature = "test-utils")]
-        let r = Ok(Self { store, dir: None });
-        r
+    /// Create a new wallet with a password in this directory.
+    ///
+    /// The database key is derived from the password with these Argon2id parameters.
+    /// Returns an error if the directory already has a wallet.
+    pub fn create_with_kdf(password: &str, dir: PathBuf, params: KdfParams) -> Result<Self, Error> {
+        if Self::exists(&dir) {
+            return Err(Error::WalletExists(dir));
+        }
+        let header = kdf::Header::new(params);
+        let key = header.derive_key(password)?;
+        std::fs::create_dir_all(&dir)?;
+        header.write_pending(&dir)?;
+        let store = match store::Store::new(&store::DbKey::Raw(&key), &db_path(&dir)) {
+            Ok(store) => store,
+            Err(e) => {
+                kdf::Header::discard_pending(&dir)?;
+                return Err(e);
+            }
+        };
+        kdf::Header::commit_pending(&dir)?;
+        Ok(Self::from_store(store, dir))
     }
 
-    /// Create a new wallet with a password.
+    /// Open an existing wallet in this directory with its password.
     ///
-    /// The wallet will find a suitable directory to store the database.
-    pub fn with_default_path(password: &str) -> anyhow::Result<Self> {
-        let path = db_dir(None)?;
-        Self::new(password, path)
+    /// Wallets created before the database key was derived with Argon2id
+    /// are migrated to the default `KdfParams`.
+    /// Returns an error if the directory has no wallet
+    /// or `Error::WrongPassword` if the password is wrong.
+    pub fn open(password: &str, dir: PathBuf) -> Result<Self, Error> {
+        if !Self::exists(&dir) {
+            return Err(Error::WalletNotFound(dir));
+        }
+        let header = kdf::Header::read(&dir)?;
+        let store = match (
+            unlock(password, &dir, header.as_ref()),
+            kdf::Header::read_pending(&dir)?,
+        ) {
+            (Ok(store), _) => {
+                kdf::Header::discard_pending(&dir)?;
+                store
+            }
+            // A rekey was interrupted after the database was rekeyed.
+            (Err(Error::WrongPassword), Some(pending)) => {
+                let store = unlock(password, &dir, Some(&pending))?;
+                kdf::Header::commit_pending(&dir)?;
+                store
+            }
+            (Err(e), _) => return Err(e),
+        };
+        let mut wallet = Self::from_store(store, dir);
+        if header.is_none() {
+            wallet.rekey(password, KdfParams::default())?;
+        }
+        Ok(wallet)
+    }
+
+    /// Whether this directory has a wallet.
+    pub fn exists(dir: &Path) -> bool {
+        db_path(dir).exists()
+    }
+
+    /// Open the wallet in this directory or create it if it doesn't exist.
+    #[deprecated(
+        note = "Use `Wallet::create` or `Wallet::open` so a wrong path is not a new wallet"
+    )]
+    pub fn new(password: &str, path: PathBuf) -> Result<Self, Error> {
+        if Self::exists(&path) {
+            Self::open(password, path)
+        } else {
+            Self::create(password, path)
+        }
+    }
+
+    /// Open the wallet in the default directory or create it if it doesn't exist.
+    #[deprecated(note = "Use `Wallet::create` or `Wallet::open` with `default_dir`")]
+    pub fn with_default_path(password: &str) -> Result<Self, Error> {
+        #[allow(deprecated)]
+        Self::new(password, default_dir())
+    }
+
+    fn from_store(store: store::Store, path: PathBuf) -> Self {
+        #[cfg(not(feature = "test-utils"))]
+        let r = Self {
+            store,
+            path,
+            confirmation: None,
+        };
+        #[cfg(feature = "test-utils")]
+        let r = Self {
+            store,
+            path,
+            confirmation: None,
+            dir: None,
+        };
+        r
     }
 
     #[cfg(feature = "test-utils")]
     /// Create a wallet for testing that has an empty password and a temporary directory.
-    pub fn temp() -> anyhow::Result<Self> {
+    ///
+    /// The database key is derived with the cheapest Argon2id parameters to keep tests fast.
+    pub fn temp() -> Result<Self, Error> {
         let dir = tempfile::tempdir()?;
-        let path = db_dir(Some(dir.path().to_path_buf()))?;
-        let mut s = Self::new("password", path)?;
+        let params = KdfParams {
+            memory_kib: argon2::Params::MIN_M_COST,
+            iterations: argon2::Params::MIN_T_COST,
+            parallelism: argon2::Params::MIN_P_COST,
+        };
+        let mut s = Self::create_with_kdf("password", dir.path().to_path_buf(), params)?;
         s.dir = Some(dir);
         Ok(s)
     }
 
+    /// Change the password of the wallet.
+    ///
+    /// The database is re-encrypted with a key derived from the new password
+    /// and a new salt using the current `KdfParams`.
+    /// The old password will no longer unlock the wallet.
+    pub fn change_password(&mut self, new_password: &str) -> Result<(), Error> {
+        let params = kdf::Header::read(&self.path)?
+            .map(|h| h.params())
+            .unwrap_or_default();
+        self.rekey(new_password, params)
+    }
+
+    /// Change the password of the wallet and the Argon2id parameters
+    /// used to derive the database key from it.
+    pub fn change_password_with_kdf(
+        &mut self,
+        new_password: &str,
+        params: KdfParams,
+    ) -> Result<(), Error> {
+        self.rekey(new_password, params)
+    }
+
+    /// Re-encrypt the database with a key derived from the password
+    /// and a new salt, then replace the header.
+    fn rekey(&mut self, password: &str, params: KdfParams) -> Result<(), Error> {
+        let header = kdf::Header::new(params);
+        let key = header.derive_key(password)?;
+        header.write_pending(&self.path)?;
+        if let Err(e) = self.store.rekey(&store::DbKey::Raw(&key)) {
+            kdf::Header::discard_pending(&self.path)?;
+            return Err(e);
+        }
+        kdf::Header::commit_pending(&self.path)
+    }
+
     #[cfg(feature = "test-utils")]
     /// Insert an existing key into the wallet.
     /// Warning this is for testing only.
-    pub fn insert_key(&mut self, name: &str, key: Key) -> anyhow::Result<()> {
-        match key {
-            Key::Secp256k1(private_key) => {
-                self.store
-                    .set_secret(name, Scheme::Secp256k1, private_key.as_ref().as_slice())
-            }
-            Key::Ed25519(_) => todo!("Not supported yet"),
-        }
+    pub fn insert_key(&mut self, name: &str, key: Key) -> Result<(), Error> {
+        self.store
+            .set_secret(name, Scheme::from(&key), &key.secret_bytes())
     }
 
     #[cfg(feature = "test-utils")]
     /// Generate a private key.
     /// Warning this is for testing only.
-    pub fn generate_private_key(&mut self, scheme: Scheme) -> anyhow::Result<Key> {
-        match scheme {
-            Scheme::Secp256k1 => {
-                let mut rng = rand::rngs::StdRng::from_entropy();
-                let (private_key, _) = secp256k1::generate_keypair(&mut rng);
-                Ok(Key::Secp256k1(private_key))
-            }
-            Scheme::Ed25519 => todo!("Not supported yet"),
-        }
+    pub fn generate_private_key(&mut self, scheme: Scheme) -> Result<Key, Error> {
+        Ok(generate_key(scheme))
     }
 
     /// Create a new key pair.
     /// The key pair will be stored in the OS self.store.
     /// The key will be stored at the name provided.
     /// The scheme determines which signature scheme to use.
-    pub fn new_key_pair(&mut self, name: &str, scheme: Scheme) -> anyhow::Result<()> {
-        match scheme {
-            Scheme::Secp256k1 => {
-                let mut rng = rand::rngs::StdRng::from_entropy();
-                let (private_key, _) = secp256k1::generate_keypair(&mut rng);
-                self.store
-                    .set_secret(name, scheme, private_key.as_ref().as_slice())
+    pub fn new_key_pair(&mut self, name: &str, scheme: Scheme) -> Result<(), Error> {
+        let key = generate_key(scheme);
+        self.store.set_secret(name, scheme, &key.secret_bytes())
+    }
+
+    /// Create a new key pair from a newly generated BIP39 mnemonic.
+    /// The key will be stored at the name provided.
+    ///
+    /// Returns the mnemonic phrase.
+    /// The key is derived at `DerivationPath::default_for(scheme, 0)`
+    /// so the phrase and passphrase can recreate the key with `restore_key_pair`.
+    /// Other BIP39 wallets can only be expected to recreate secp256k1 keys.
+    pub fn new_key_pair_with_mnemonic(
+        &mut self,
+        name: &str,
+        scheme: Scheme,
+        length: MnemonicLength,
+        passphrase: &str,
+    ) -> Result<Zeroizing<String>, Error> {
+        let mnemonic = mnemonic::generate(length)?;
+        let key = mnemonic::to_key(&mnemonic, passphrase, scheme)?;
+        self.store.set_secret(name, scheme, &key.secret_bytes())?;
+        Ok(Zeroizing::new(mnemonic.to_string()))
+    }
+
+    /// Recreate a key pair from a BIP39 mnemonic phrase and passphrase.
+    /// The key will be stored at the name provided.
+    pub fn restore_key_pair(
+        &mut self,
+        name: &str,
+        scheme: Scheme,
+        phrase: &str,
+        passphrase: &str,
+    ) -> Result<(), Error> {
+        let mnemonic = mnemonic::parse(phrase)?;
+        let key = mnemonic::to_key(&mnemonic, passphrase, scheme)?;
+        self.store.set_secret(name, scheme, &key.secret_bytes())
+    }
+
+    /// Import an existing private key.
+    /// The key will be stored at the name provided.
+    ///
+    /// The data can be a raw key in the given encoding, a `<scheme>:<data>` string
+    /// or a PKCS#8 or SEC1 EC key as PEM or DER.
+    /// The scheme is detected for all formats except raw keys which require it.
+    /// If the scheme is given it must match the scheme of the key.
+    /// Returns the scheme of the imported key.
+    pub fn import_key(
+        &mut self,
+        name: &str,
+        data: &[u8],
+        encoding: Encoding,
+        scheme: Option<Scheme>,
+    ) -> Result<Scheme, Error> {
+        self.import_key_with_passphrase(name, data, encoding, scheme, None)
+    }
+
+    /// Import an existing private key which may be encrypted PKCS#8.
+    ///
+    /// Like `import_key` but encrypted PKCS#8 keys, as PEM or DER,
+    /// are decrypted with the passphrase.
+    /// Returns `Error::MissingPassphrase` if the key is encrypted and no passphrase is given.
+    pub fn import_key_with_passphrase(
+        &mut self,
+        name: &str,
+        data: &[u8],
+        encoding: Encoding,
+        scheme: Option<Scheme>,
+        passphrase: Option<&str>,
+    ) -> Result<Scheme, Error> {
+        let key = import::parse_key(data, encoding, scheme, passphrase)?;
+        let scheme = Scheme::from(&key);
+        self.store.set_secret(name, scheme, &key.secret_bytes())?;
+        Ok(scheme)
+    }
+
+    /// Import a secp256k1 key from Ethereum keystore v3 JSON.
+    /// The key will be stored at the name provided.
+    ///
+    /// Both scrypt and pbkdf2 keystores are supported,
+    /// except scrypt keystores with n >= 2^(16 * r) which RFC 7914 forbids.
+    /// Returns an error if the MAC does not match the passphrase.
+    pub fn import_keystore(
+        &mut self,
+        name: &str,
+        json: &str,
+        passphrase: &str,
+    ) -> Result<(), Error> {
+        let key = keystore::decrypt(json, passphrase)?;
+        self.store
+            .set_secret(name, Scheme::Secp256k1, &key.secret_bytes())
+    }
+
+    /// Create the master seed for this wallet from a newly generated BIP39 mnemonic.
+    ///
+    /// Returns the mnemonic phrase.
+    /// The phrase and passphrase can recreate the seed with `restore_seed`.
+    /// Returns an error if the wallet already has a seed.
+    pub fn create_seed(
+        &mut self,
+        length: MnemonicLength,
+        passphrase: &str,
+    ) -> Result<Zeroizing<String>, Error> {
+        let mnemonic = mnemonic::generate(length)?;
+        self.store
+            .set_seed(&mnemonic::to_seed(&mnemonic, passphrase))?;
+        Ok(Zeroizing::new(mnemonic.to_string()))
+    }
+
+    /// Recreate the master seed for this wallet from a BIP39 mnemonic phrase and passphrase.
+    ///
+    /// Returns an error if the wallet already has a seed.
+    pub fn restore_seed(&mut self, phrase: &str, passphrase: &str) -> Result<(), Error> {
+        let mnemonic = mnemonic::parse(phrase)?;
+        self.store
+            .set_seed(&mnemonic::to_seed(&mnemonic, passphrase))
+    }
+
+    /// Create a new key pair derived from the wallet's master seed.
+    /// The key will be stored at the name provided along with its derivation path.
+    ///
+    /// If no path is given the next unused `DerivationPath::default_for` the scheme is used.
+    /// Secp256k1 keys use BIP32 and ed25519 keys use SLIP-0010.
+    /// Returns the path the key was derived at.
+    pub fn new_derived_key_pair(
+        &mut self,
+        name: &str,
+        scheme: Scheme,
+        path: Option<DerivationPath>,
+    ) -> Result<DerivationPath, Error> {
+        let seed = self.store.get_seed()?;
+        let path = match path {
+            Some(path) => path,
+            None => {
+                let used: HashSet<DerivationPath> = self
+                    .store
+                    .list()?
+                    .into_iter()
+                    .filter_map(|k| k.derivation_path)
+                    .collect();
+                (0..)
+                    .map(|i| DerivationPath::default_for(scheme, i))
+                    .find(|p| !used.contains(p))
+                    .expect("There are more indices than keys")
             }
-            Scheme::Ed25519 => todo!("Not supported yet"),
-        }
+        };
+        let key = hd::derive(seed.expose(), &path, scheme)?;
+        self.store
+            .set_derived_secret(name, scheme, &key.secret_bytes(), &path.to_string())?;
+        Ok(path)
+    }
+
+    /// List all names for key pairs along with their derivation path
+    /// if they were derived from the wallet's master seed.
+    pub fn list_derivation_paths(
+        &mut self,
+    ) -> Result<Vec<(String, Option<DerivationPath>)>, Error> {
+        Ok(self
+            .store
+            .list()?
+            .into_iter()
+            .map(|k| (k.name, k.derivation_path))
+            .collect())
     }
 
     /// Delete a key pair at this name.
-    pub fn delete_key_pair(&mut self, name: &str) -> anyhow::Result<()> {
+    pub fn delete_key_pair(&mut self, name: &str) -> Result<(), Error> {
         self.store.delete_secret(name)
     }
 
+    /// Rename the key pair at this name.
+    ///
+    /// Returns an error if a key pair is already stored at the new name.
+    pub fn rename_key_pair(&mut self, name: &str, new_name: &str) -> Result<(), Error> {
+        self.store.rename_secret(name, new_name)
+    }
+
     /// List all names for key pairs stored in the OS self.store for this service.
-    pub fn list_names(&mut self) -> anyhow::Result<Vec<String>> {
-        Ok(self.list()?.into_iter().map(|(n, _)| n).collect())
+    pub fn list_names(&mut self) -> Result<Vec<String>, Error> {
+        Ok(self.store.list()?.into_iter().map(|k| k.name).collect())
+    }
+
+    /// List information about all stored key pairs in the order they were stored.
+    pub fn list_keys(&mut self) -> Result<Vec<KeyInfo>, Error> {
+        self.store.list()
+    }
+
+    /// Get information about the key pair at this name.
+    pub fn key_info(&mut self, name: &str) -> Result<KeyInfo, Error> {
+        self.store.key_info(name)
+    }
+
+    /// Set or clear the description of the key pair at this name.
+    pub fn set_description(&mut self, name: &str, description: Option<&str>) -> Result<(), Error> {
+        self.store.set_description(name, description)
+    }
+
+    /// Replace the tags of the key pair at this name.
+    pub fn set_tags(&mut self, name: &str, tags: &[String]) -> Result<(), Error> {
+        self.store.set_tags(name, tags)
+    }
+
+    /// Replace the signing policy of the key pair at this name.
+    ///
+    /// The policy is checked before every signature the key pair makes.
+    pub fn set_policy(&mut self, name: &str, policy: &SigningPolicy) -> Result<(), Error> {
+        self.store.set_policy(name, policy)
+    }
+
+    /// Set how signatures are confirmed for key pairs whose policy requires it.
+    ///
+    /// Without a confirmation these key pairs can't sign.
+    pub fn set_confirmation(
+        &mut self,
+        confirmation: impl FnMut(&str, SignOperation, &Hash) -> bool + Send + 'static,
+    ) {
+        self.confirmation = Some(Box::new(confirmation));
+    }
+
+    /// Store a public key without its secret key at this name.
+    ///
+    /// Watch-only keys can't sign but can be used to check signatures.
+    /// Key pairs and watch-only keys can't share a name.
+    pub fn add_watch_only_key(&mut self, name: &str, public_key: &PublicKey) -> Result<(), Error> {
+        self.store.set_watch_only(name, public_key)
+    }
+
+    /// Delete the watch-only key at this name.
+    pub fn delete_watch_only_key(&mut self, name: &str) -> Result<(), Error> {
+        self.store.delete_watch_only(name)
+    }
+
+    /// List all watch-only keys in the order they were stored.
+    pub fn list_watch_only_keys(&mut self) -> Result<Vec<WatchOnlyKey>, Error> {
+        self.store.list_watch_only()
+    }
+
+    /// Get the public key stored at this name,
+    /// either of a key pair or a watch-only key.
+    pub fn lookup_public_key(&mut self, name: &str) -> Result<PublicKey, Error> {
+        match self.get_public_key(name) {
+            Err(Error::KeyNotFound(_)) => Ok(self.store.get_watch_only(name)?.public_key),
+            result => result,
+        }
+    }
+
+    /// Find the names of the key pairs and watch-only keys with this public key.
+    ///
+    /// Key pairs are listed before watch-only keys, each in the order they were stored.
+    pub fn find_by_public_key(&mut self, public_key: &PublicKey) -> Result<Vec<String>, Error> {
+        self.find(|key| key == public_key)
+    }
+
+    /// Find the names of the key pairs and watch-only keys whose public key has this hash.
+    ///
+    /// The hash is the `public_key_hash` as used on chain.
+    pub fn find_by_pubkey_hash(&mut self, hash: &Hash) -> Result<Vec<String>, Error> {
+        self.find(|key| public_key_hash(key) == *hash)
+    }
+
+    /// Find the names of the key pairs and watch-only keys that made this signature over the hash.
+    ///
+    /// Unlike recovering the public key this also works for ed25519 signatures.
+    pub fn find_by_signature(
+        &mut self,
+        hash: Hash,
+        signature: &Signature,
+    ) -> Result<Vec<String>, Error> {
+        self.find(|key| essential_signer::verify_hash(hash, signature, key).is_ok())
+    }
+
+    /// The names of all key pairs and watch-only keys whose public key matches.
+    ///
+    /// Only the stored public keys are read so no private key is decrypted
+    /// and keys that can't be read are skipped.
+    fn find(&mut self, mut matches: impl FnMut(&PublicKey) -> bool) -> Result<Vec<String>, Error> {
+        Ok(self
+            .store
+            .public_keys()?
+            .into_iter()
+            .filter(|(_, key)| matches(key))
+            .map(|(name, _)| name)
+            .collect())
     }
 
     /// Get the public key for this key pair.
-    pub fn get_public_key(&mut self, name: &str) -> anyhow::Result<PublicKey> {
+    pub fn get_public_key(&mut self, name: &str) -> Result<PublicKey, Error> {
         let key = self.name_to_key(name)?;
         Ok(essential_signer::public_key(&key))
     }
 
     /// Get the private key for this key pair.
-    pub fn get_private_key(&mut self, name: &str) -> anyhow::Result<Key> {
+    ///
+    /// The key is zeroized when dropped.
+    pub fn get_private_key(&mut self, name: &str) -> Result<Key, Error> {
         self.name_to_key(name)
     }
 
+    /// Export the private key in this format.
+    ///
+    /// The passphrase is required for encrypted formats and ignored otherwise.
+    /// Warning: Unencrypted formats contain the secret key.
+    pub fn export_key(
+        &mut self,
+        name: &str,
+        format: ExportFormat,
+        passphrase: Option<&str>,
+    ) -> Result<Zeroizing<String>, Error> {
+        let key = self.name_to_key(name)?;
+        export::export_key(&key, format, passphrase)
+    }
+
+    /// Export the private key in this format to a new file.
+    ///
+    /// On unix the file is only readable and writable by the owner.
+    /// Returns an error if the file already exists.
+    pub fn export_key_to_file(
+        &mut self,
+        name: &str,
+        format: ExportFormat,
+        passphrase: Option<&str>,
+        path: &Path,
+    ) -> Result<(), Error> {
+        let exported = self.export_key(name, format, passphrase)?;
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options.open(path)?.write_all(exported.as_bytes())?;
+        Ok(())
+    }
+
     /// Sign an contract.
     ///
     /// Requires the keypair be a secp256k1 key or this will return an error.
@@ -155,14 +598,17 @@ impl Wallet {
         &mut self,
         data: Contract,
         name: &str,
-    ) -> anyhow::Result<essential_types::contract::SignedContract> {
-        let key = self.name_to_key(name)?;
-        match key {
-            Key::Secp256k1(key) => Ok(essential_sign::contract::sign(data, &key)),
-            Key::Ed25519(_) => Err(anyhow::anyhow!(
-                "Ed25519 not supported for signing contracts. Please use a Secp256k1 key"
-            ))?,
-        }
+    ) -> Result<essential_types::contract::SignedContract, Error> {
+        let hash = essential_hash::content_addr(&data).0;
+        let size = essential_signer::postcard_bytes(&data)?.len();
+        let Signature::Secp256k1(sig) = self.sign(name, SignOperation::Contract, hash, size)?
+        else {
+            unreachable!("Only secp256k1 keys sign contracts");
+        };
+        Ok(essential_types::contract::SignedContract {
+            contract: data,
+            signature: essential_signer::to_essential_signature(sig)?,
+        })
     }
 
     /// Create a signature using the key pair stored at this name.
@@ -173,9 +619,24 @@ impl Wallet {
         &mut self,
         data: &T,
         name: &str,
-    ) -> anyhow::Result<Signature> {
-        let key = self.name_to_key(name)?;
-        essential_signer::sign_postcard(data, &key)
+    ) -> Result<Signature, Error> {
+        self.sign_postcard_with_hasher(data, HashAlgorithm::Sha256, name)
+    }
+
+    /// Create a signature using the key pair stored at this name.
+    ///
+    /// The data will be serialized as postcard, then hashed with the given algorithm
+    /// and the hash signed.
+    /// No padding is applied to the data before signing.
+    pub fn sign_postcard_with_hasher<T: Serialize>(
+        &mut self,
+        data: &T,
+        hasher: HashAlgorithm,
+        name: &str,
+    ) -> Result<Signature, Error> {
+        let data = essential_signer::postcard_bytes(data)?;
+        let hash = hasher.hash(&data);
+        self.sign(name, SignOperation::Postcard, hash, data.len())
     }
 
     /// Create a signature using the key pair stored at this name.
@@ -187,21 +648,36 @@ impl Wallet {
         data: &T,
         padding: Padding,
         name: &str,
-    ) -> anyhow::Result<Signature> {
-        let key = self.name_to_key(name)?;
-        essential_signer::sign_postcard_with_padding(data, padding, &key)
+    ) -> Result<Signature, Error> {
+        self.sign_postcard_with_padding_and_hasher(data, padding, HashAlgorithm::Sha256, name)
     }
 
     /// Create a signature using the key pair stored at this name.
-    pub fn sign_hash(&mut self, data: Hash, name: &str) -> anyhow::Result<Signature> {
-        let key = self.name_to_key(name)?;
-        essential_signer::sign_hash(data, &key)
+    ///
+    /// The data will be serialized as postcard, then padded to be word aligned,
+    /// then hashed with the given algorithm and the hash signed.
+    pub fn sign_postcard_with_padding_and_hasher<T: Serialize>(
+        &mut self,
+        data: &T,
+        padding: Padding,
+        hasher: HashAlgorithm,
+        name: &str,
+    ) -> Result<Signature, Error> {
+        let data = essential_signer::postcard_bytes_with_padding(data, padding)?;
+        let hash = hasher.hash(&data);
+        self.sign(name, SignOperation::Postcard, hash, data.len())
     }
 
     /// Create a signature using the key pair stored at this name.
-    pub fn sign_words(&mut self, data: &[Word], name: &str) -> anyhow::Result<Signature> {
-        let key = self.name_to_key(name)?;
-        essential_signer::sign_words(data, &key)
+    pub fn sign_hash(&mut self, data: Hash, name: &str) -> Result<Signature, Error> {
+        self.sign(name, SignOperation::Hash, data, data.len())
+    }
+
+    /// Create a signature using the key pair stored at this name.
+    pub fn sign_words(&mut self, data: &[Word], name: &str) -> Result<Signature, Error> {
+        let hash = essential_signer::hash_words(data);
+        let size = std::mem::size_of_val(data);
+        self.sign(name, SignOperation::Words, hash, size)
     }
 
     /// Create a signature using the key pair stored at this name.
@@ -212,43 +688,204 @@ impl Wallet {
         data: Vec<u8>,
         padding: Padding,
         name: &str,
-    ) -> anyhow::Result<Signature> {
-        let key = self.name_to_key(name)?;
-        essential_signer::sign_bytes_with_padding(data, padding, &key)
+    ) -> Result<Signature, Error> {
+        self.sign_bytes_with_padding_and_hasher(data, padding, HashAlgorithm::Sha256, name)
+    }
+
+    /// Create a signature using the key pair stored at this name.
+    ///
+    /// The data will be padded to be word aligned, then hashed with the given algorithm
+    /// and the hash signed.
+    pub fn sign_bytes_with_padding_and_hasher(
+        &mut self,
+        data: Vec<u8>,
+        padding: Padding,
+        hasher: HashAlgorithm,
+        name: &str,
+    ) -> Result<Signature, Error> {
+        let data = essential_signer::align_to_word(data, padding);
+        let hash = hasher.hash(&data);
+        self.sign(name, SignOperation::Bytes, hash, data.len())
     }
 
     /// Create a signature using the key pair stored at this name.
     ///
     /// The data will be hashed and the hash signed.
     /// This will return an error if the data is not word aligned.
-    pub fn sign_aligned_bytes(&mut self, data: &[u8], name: &str) -> anyhow::Result<Signature> {
-        let key = self.name_to_key(name)?;
-        essential_signer::sign_aligned_bytes(data, &key)
+    pub fn sign_aligned_bytes(&mut self, data: &[u8], name: &str) -> Result<Signature, Error> {
+        self.sign_aligned_bytes_with_hasher(data, HashAlgorithm::Sha256, name)
+    }
+
+    /// Create a signature using the key pair stored at this name.
+    ///
+    /// The data will be hashed with the given algorithm and the hash signed.
+    /// This will return an error if the data is not word aligned.
+    pub fn sign_aligned_bytes_with_hasher(
+        &mut self,
+        data: &[u8],
+        hasher: HashAlgorithm,
+        name: &str,
+    ) -> Result<Signature, Error> {
+        if !essential_signer::is_word_aligned(data) {
+            return Err(essential_signer::Error::NotWordAligned.into());
+        }
+        let hash = hasher.hash(data);
+        self.sign(name, SignOperation::Bytes, hash, data.len())
     }
 
     /// Create a signature using the key pair stored at this name.
     ///
     /// The data will be hashed and the hash signed.
     /// Word alignment is not checked.
-    pub fn sign_bytes_unchecked(&mut self, data: &[u8], name: &str) -> anyhow::Result<Signature> {
-        let key = self.name_to_key(name)?;
-        essential_signer::sign_bytes_unchecked(data, &key)
+    pub fn sign_bytes_unchecked(&mut self, data: &[u8], name: &str) -> Result<Signature, Error> {
+        self.sign_bytes_unchecked_with_hasher(data, HashAlgorithm::Sha256, name)
     }
 
-    fn name_to_key(&mut self, name: &str) -> anyhow::Result<Key> {
-        let (private_key, scheme) = self.store.get_secret(name)?;
-        match scheme {
-            Scheme::Secp256k1 => {
-                let private_key = secp256k1::SecretKey::from_slice(private_key.as_slice())?;
-                Ok(Key::Secp256k1(private_key))
+    /// Create a signature using the key pair stored at this name.
+    ///
+    /// The data will be hashed with the given algorithm and the hash signed.
+    /// Word alignment is not checked.
+    pub fn sign_bytes_unchecked_with_hasher(
+        &mut self,
+        data: &[u8],
+        hasher: HashAlgorithm,
+        name: &str,
+    ) -> Result<Signature, Error> {
+        let hash = hasher.hash(data);
+        self.sign(name, SignOperation::Bytes, hash, data.len())
+    }
+
+    /// Create a signature using the key pair stored at this name.
+    ///
+    /// Exactly `len` bytes are read from the reader and hashed with the given algorithm
+    /// as they are read so the data is never fully held in memory.
+    /// If a padding is given the data is hashed as if it was padded to be word aligned,
+    /// otherwise word alignment is not checked.
+    /// The policy of the key pair is checked against `len` before anything is read.
+    /// Returns an error if the reader does not produce exactly `len` bytes.
+    pub fn sign_reader<R: Read>(
+        &mut self,
+        reader: R,
+        len: u64,
+        padding: Option<Padding>,
+        hasher: HashAlgorithm,
+        name: &str,
+    ) -> Result<Signature, Error> {
+        let size = match padding {
+            Some(_) => len.div_ceil(8) * 8,
+            None => len,
+        };
+        let (key, policy) = self.authorize(name, SignOperation::Bytes, size)?;
+        let hash = match padding {
+            Some(padding) => {
+                essential_signer::hash_reader_with_padding(reader, len, padding, hasher)?
+            }
+            None => {
+                let mut hasher = hasher.hasher();
+                let read = hasher.update_reader(reader)?;
+                if read != len {
+                    return Err(essential_signer::Error::ReaderLengthMismatch(len, read).into());
+                }
+                hasher.finalize()
             }
-            Scheme::Ed25519 => todo!("Not supported yet"),
+        };
+        self.sign_authorized(name, &key, &policy, SignOperation::Bytes, hash)
+    }
+
+    /// List the signatures recorded in the audit log in the order they were made.
+    ///
+    /// If a name is given only signatures made by the key pair
+    /// stored under that name at the time are listed.
+    pub fn audit_log(&mut self, name: Option<&str>) -> Result<Vec<AuditEntry>, Error> {
+        self.store.audit_log(name)
+    }
+
+    /// Check that no entry of the audit log has been changed or deleted.
+    ///
+    /// Returns the number of entries or `Error::AuditLogTampered`
+    /// with the first entry that does not match the chain.
+    /// The chain is not keyed so this does not detect a log rewritten
+    /// by someone with the password.
+    pub fn verify_audit_log(&mut self) -> Result<u64, Error> {
+        self.store.verify_audit_log()
+    }
+
+    fn name_to_key(&mut self, name: &str) -> Result<Key, Error> {
+        let (private_key, scheme) = self.store.get_secret(name)?;
+        secret_to_key(&private_key, scheme)
+            .ok_or_else(|| Error::InvalidStoredKey(name.to_string(), scheme))
+    }
+
+    /// Sign the hash with the key pair stored at this name.
+    ///
+    /// All signing goes through here or `sign_reader`
+    /// so every signature is checked against the key pair's policy
+    /// and recorded in the audit log.
+    /// The size is the size in bytes of the input that was hashed.
+    fn sign(
+        &mut self,
+        name: &str,
+        operation: SignOperation,
+        hash: Hash,
+        size: usize,
+    ) -> Result<Signature, Error> {
+        let (key, policy) = self.authorize(name, operation, size as u64)?;
+        self.sign_authorized(name, &key, &policy, operation, hash)
+    }
+
+    /// Load the key pair stored at this name and check its policy allows
+    /// signing an input of this size with this operation.
+    ///
+    /// This is done before the input is hashed so large inputs are rejected early.
+    fn authorize(
+        &mut self,
+        name: &str,
+        operation: SignOperation,
+        size: u64,
+    ) -> Result<(Key, SigningPolicy), Error> {
+        let key = self.name_to_key(name)?;
+        let scheme = Scheme::from(&key);
+        if operation == SignOperation::Contract && scheme != Scheme::Secp256k1 {
+            return Err(Error::UnsupportedScheme(scheme, "signing contracts"));
         }
+        let policy = self.store.key_info(name)?.policy;
+        policy
+            .check(operation, size)
+            .map_err(|v| Error::PolicyViolation(name.to_string(), v))?;
+        Ok((key, policy))
     }
 
-    /// List all accounts under this service.
-    fn list(&mut self) -> anyhow::Result<Vec<(String, Scheme)>> {
-        self.store.list()
+    /// Ask for confirmation if the policy requires it,
+    /// then sign the hash and record the signature in the audit log.
+    fn sign_authorized(
+        &mut self,
+        name: &str,
+        key: &Key,
+        policy: &SigningPolicy,
+        operation: SignOperation,
+        hash: Hash,
+    ) -> Result<Signature, Error> {
+        if policy.require_confirmation {
+            let confirmed = self
+                .confirmation
+                .as_mut()
+                .is_some_and(|confirm| confirm(name, operation, &hash));
+            if !confirmed {
+                return Err(Error::PolicyViolation(
+                    name.to_string(),
+                    PolicyViolation::NotConfirmed,
+                ));
+            }
+        }
+        let sig = essential_signer::sign_hash(hash, key)?;
+        self.store.record_signature(
+            name,
+            Scheme::from(key),
+            operation,
+            &hash,
+            &audit::signature_hash(&sig)?,
+        )?;
+        Ok(sig)
     }
 }
 
@@ -261,35 +898,100 @@ impl Display for Scheme {
     }
 }
 
+impl From<&Key> for Scheme {
+    fn from(key: &Key) -> Self {
+        match key {
+            Key::Secp256k1(_) => Scheme::Secp256k1,
+            Key::Ed25519(_) => Scheme::Ed25519,
+        }
+    }
+}
+
+impl From<&PublicKey> for Scheme {
+    fn from(key: &PublicKey) -> Self {
+        match key {
+            PublicKey::Secp256k1(_) => Scheme::Secp256k1,
+            PublicKey::Ed25519(_) => Scheme::Ed25519,
+        }
+    }
+}
+
 impl FromStr for Scheme {
-    type Err = anyhow::Error;
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "secp256k1" => Ok(Scheme::Secp256k1),
             "ed25519" => Ok(Scheme::Ed25519),
-            _ => Err(anyhow::anyhow!("Unknown scheme: {}", s)),
+            _ => Err(Error::UnknownScheme(s.to_string())),
         }
     }
 }
 
-fn db_dir(in_path: Option<PathBuf>) -> anyhow::Result<PathBuf> {
-    let path = match in_path {
-        None => {
-            let mut path = dirs::home_dir().unwrap_or_else(|| {
-                dirs::document_dir().unwrap_or_else(|| {
-                    dirs::data_local_dir()
-                        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR").to_string()))
-                })
-            });
-            path.push(format!(".{}", NAME));
-            path
+/// The key for these secret bytes if they are a valid key of the scheme.
+fn secret_to_key(secret: &SecretBytes, scheme: Scheme) -> Option<Key> {
+    match scheme {
+        Scheme::Secp256k1 => secp256k1::SecretKey::from_slice(secret.expose())
+            .ok()
+            .map(Key::Secp256k1),
+        Scheme::Ed25519 => secret.expose().try_into().ok().map(Key::Ed25519),
+    }
+}
+
+/// Generate a new random private key for the given scheme.
+fn generate_key(scheme: Scheme) -> Key {
+    let mut rng = rand::rngs::StdRng::from_entropy();
+    match scheme {
+        Scheme::Secp256k1 => {
+            let (private_key, _) = secp256k1::generate_keypair(&mut rng);
+            Key::Secp256k1(private_key)
         }
-        Some(path) => path,
-    };
+        Scheme::Ed25519 => {
+            let mut key = Key::Ed25519(ed25519_dalek::SecretKey::default());
+            if let Key::Ed25519(private_key) = &mut key {
+                rng.fill_bytes(private_key);
+            }
+            key
+        }
+    }
+}
 
-    if !path.is_dir() {
-        std::fs::create_dir_all(&path)?;
+/// The hash of the word encoded public key.
+///
+/// This is how public keys are usually referred to on chain
+/// and what `print-pub-key --hashed` prints.
+pub fn public_key_hash(public_key: &PublicKey) -> Hash {
+    essential_signer::hash_words(&essential_signer::public_key_to_words(public_key))
+}
+
+/// The directory the wallet is stored in when no directory is given.
+///
+/// This is `.essential-wallet` in the home directory
+/// or another suitable directory if there is no home directory.
+pub fn default_dir() -> PathBuf {
+    let mut path = dirs::home_dir().unwrap_or_else(|| {
+        dirs::document_dir().unwrap_or_else(|| {
+            dirs::data_local_dir()
+                .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR").to_string()))
+        })
+    });
+    path.push(format!(".{}", NAME));
+    path
+}
+
+/// Unlock the database with a key derived from the password
+/// or the password itself if there is no header.
+fn unlock(password: &str, dir: &Path, header: Option<&kdf::Header>) -> Result<store::Store, Error> {
+    let path = db_path(dir);
+    match header {
+        Some(header) => {
+            let key = header.derive_key(password)?;
+            store::Store::new(&store::DbKey::Raw(&key), &path)
+        }
+        None => store::Store::new(&store::DbKey::Legacy(password), &path),
     }
-    Ok(path)
+}
+
+fn db_path(dir: &Path) -> PathBuf {
+    dir.join(DB_FILE)
 }
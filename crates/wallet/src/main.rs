@@ -1,10 +1,17 @@
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 
 use anyhow::ensure;
-use clap::{Parser, Subcommand};
-use essential_signer::{decode_str, read_file, Encoding, Padding, Signature};
-use essential_types::{contract::Contract, convert::bytes_from_word};
-use essential_wallet::{Scheme, Wallet};
+use clap::{value_parser, ArgGroup, Args, Parser, Subcommand};
+use essential_signer::{
+    decode_str, read_file, Encoding, HashAlgorithm, Padding, PublicKey, Signature,
+};
+use essential_types::{contract::Contract, convert::bytes_from_word, Hash, Word};
+use essential_wallet::{
+    DerivationPath, ExportFormat, KdfParams, MnemonicLength, Scheme, SignOperation, SigningPolicy,
+    Wallet,
+};
+use zeroize::Zeroizing;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -23,18 +30,173 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Command {
+    /// Create a new wallet.
+    ///
+    /// The password will be prompted for twice if not given.
+    Init {
+        #[command(flatten)]
+        kdf: KdfArgs,
+    },
     Generate {
         /// The name that the key pair will be stored under.
         name: String,
         /// The signature scheme to use.
         #[arg(default_value_t = Scheme::Secp256k1, value_enum)]
         scheme: Scheme,
+        /// Generate the key from a new mnemonic with this many words
+        /// and print the mnemonic so it can be backed up.
+        /// The key is derived at m/44'/60'/0'/0/0 for secp256k1
+        /// so other BIP39 wallets can restore it,
+        /// and at m/44'/60'/0'/0'/0' for ed25519 which only this wallet is known to use.
+        #[arg(short, long, value_enum)]
+        mnemonic: Option<MnemonicLength>,
+        /// Optional BIP39 passphrase used with the mnemonic.
+        #[arg(long, requires = "mnemonic")]
+        passphrase: Option<String>,
+        /// Derive the key from the wallet seed at the next unused default path.
+        #[arg(short, long, conflicts_with = "mnemonic")]
+        derive: bool,
+        /// Derive the key from the wallet seed at this path (like m/44'/60'/0'/0/0).
+        #[arg(long, conflicts_with_all = ["mnemonic", "derive"])]
+        path: Option<DerivationPath>,
+    },
+    /// Manage the master seed that keys are derived from.
+    Seed {
+        #[command(subcommand)]
+        command: SeedCommand,
+    },
+    /// Manage public keys stored without their secret keys.
+    ///
+    /// Watch-only keys can be used to verify and recover signatures by name.
+    Watch {
+        #[command(subcommand)]
+        command: WatchCommand,
+    },
+    /// Restore a key pair from a BIP39 mnemonic.
+    ///
+    /// The key is derived at m/44'/60'/0'/0/0 for secp256k1
+    /// and m/44'/60'/0'/0'/0' for ed25519.
+    /// An ed25519 key from another wallet may have used a different path.
+    /// The mnemonic will be prompted for.
+    Restore {
+        /// The name that the key pair will be stored under.
+        name: String,
+        /// The signature scheme the key was generated with.
+        #[arg(default_value_t = Scheme::Secp256k1, value_enum)]
+        scheme: Scheme,
+        /// Optional BIP39 passphrase used with the mnemonic.
+        #[arg(long)]
+        passphrase: Option<String>,
+    },
+    /// Import an existing private key.
+    ///
+    /// Accepts raw keys in any encoding, `<scheme>:<data>` strings,
+    /// PKCS#8, encrypted PKCS#8 or SEC1 EC keys as PEM or DER
+    /// and Ethereum keystore v3 files.
+    /// The key will be prompted for if no file is given.
+    Import {
+        /// The name that the key pair will be stored under.
+        name: String,
+        /// The signature scheme of the key.
+        /// Required for raw keys, otherwise it is detected.
+        #[arg(long, value_enum)]
+        scheme: Option<Scheme>,
+        /// Path to a file containing the key.
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+        /// Encoding of raw keys.
+        #[arg(short, long, default_value_t = Encoding::Hex, value_enum)]
+        encoding: Encoding,
+        /// The key is an Ethereum keystore v3 file.
+        /// The keystore passphrase will be prompted for if not given.
+        /// Scrypt keystores with n >= 2^(16 * r), like those with r = 1, are not supported.
+        #[arg(short, long, requires = "file", conflicts_with_all = ["scheme", "encoding"])]
+        keystore: bool,
+        /// Passphrase used to decrypt the keystore or an encrypted PKCS#8 key.
+        /// It will be prompted for if the key is encrypted and it is not given.
+        #[arg(long, requires = "file")]
+        passphrase: Option<String>,
+    },
+    /// Export a private key to use in other tools.
+    ///
+    /// The passphrase for encrypted formats will be prompted for if not given.
+    Export {
+        /// The name of the key pair to export.
+        name: String,
+        /// The format to export the key in.
+        #[arg(short, long, default_value_t = ExportFormat::Pkcs8, value_enum)]
+        format: ExportFormat,
+        /// Write the key to this new file instead of printing it.
+        /// On unix the file is only readable and writable by the owner.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Passphrase used to encrypt the exported key.
+        #[arg(long)]
+        passphrase: Option<String>,
     },
     Delete {
         /// The name of the key pair to delete.
         name: String,
     },
-    List,
+    /// Rename a key pair.
+    Rename {
+        /// The current name of the key pair.
+        name: String,
+        /// The new name for the key pair.
+        new_name: String,
+    },
+    /// Set the description or tags of a key pair.
+    #[command(group(
+        ArgGroup::new("label")
+            .required(true)
+            .multiple(true)
+            .args(["description", "clear_description", "tags"])
+    ))]
+    Label {
+        /// The name of the key pair to label.
+        name: String,
+        /// A free-form description of the key pair.
+        #[arg(short, long)]
+        description: Option<String>,
+        /// Remove the description of the key pair.
+        #[arg(long, conflicts_with = "description")]
+        clear_description: bool,
+        /// Comma separated tags which replace the current tags.
+        /// Pass an empty string to remove all tags.
+        #[arg(short, long, value_delimiter = ',')]
+        tags: Option<Vec<String>>,
+    },
+    /// Show or replace the signing policy of a key pair.
+    ///
+    /// With no options the current policy is shown.
+    /// Otherwise the policy is replaced and options that are not given are unrestricted.
+    Policy {
+        /// The name of the key pair.
+        name: String,
+        /// Comma separated operations the key pair may sign with.
+        #[arg(short, long, value_enum, value_delimiter = ',')]
+        allow: Option<Vec<SignOperation>>,
+        /// The largest input in bytes the key pair may sign.
+        #[arg(short, long)]
+        max_input_size: Option<u64>,
+        /// When the key pair stops being allowed to sign (like 2030-01-01T00:00:00Z).
+        #[arg(short, long, value_parser = humantime::parse_rfc3339_weak, conflicts_with = "expires_in")]
+        expires: Option<SystemTime>,
+        /// How long until the key pair stops being allowed to sign (like 30days).
+        #[arg(long, value_parser = humantime::parse_duration)]
+        expires_in: Option<Duration>,
+        /// Ask for confirmation before each signature.
+        #[arg(short, long)]
+        confirm: bool,
+        /// Remove all restrictions.
+        #[arg(long, conflicts_with_all = ["allow", "max_input_size", "expires", "expires_in", "confirm"])]
+        clear: bool,
+    },
+    List {
+        /// Show the scheme, times, tags and description of each key pair.
+        #[arg(short, long)]
+        long: bool,
+    },
     Sign {
         /// The name of the key pair to use for signing.
         name: String,
@@ -47,6 +209,9 @@ enum Command {
         /// auto-pad the input to the nearest Word (8 bytes)
         #[arg(short, long, value_enum)]
         auto_pad: Option<Padding>,
+        /// The algorithm used to hash the input before signing.
+        #[arg(long, default_value_t = HashAlgorithm::Sha256, value_enum)]
+        hash: HashAlgorithm,
         /// Encoding of the output signature
         #[arg(short, long, default_value_t = Encoding::Hex, value_enum)]
         output: Encoding,
@@ -63,10 +228,113 @@ enum Command {
         #[arg(short, long, default_value_t = Encoding::Hex, value_enum)]
         output: Encoding,
     },
+    /// Verify a signature.
+    ///
+    /// Exits with a non-zero status if the signature is invalid.
+    Verify {
+        /// The signature to verify.
+        /// Both padded and unpadded signatures are accepted.
+        signature: String,
+        /// Encoding of the signature.
+        #[arg(short, long, default_value_t = Encoding::Hex, value_enum)]
+        signature_encoding: Encoding,
+        /// The name of the stored key pair or watch-only key to verify against.
+        #[arg(
+            short,
+            long,
+            required_unless_present = "public_key",
+            conflicts_with = "public_key"
+        )]
+        name: Option<String>,
+        /// The public key to verify against.
+        /// Both padded and unpadded public keys are accepted.
+        #[arg(long)]
+        public_key: Option<String>,
+        /// Encoding of the public key.
+        #[arg(long, default_value_t = Encoding::HexUpper, value_enum)]
+        public_key_encoding: Encoding,
+        /// Require the input to be word aligned [default: true].
+        #[arg(short, long, default_value_t = true)]
+        require_aligned: bool,
+        /// The input that was signed.
+        #[command(subcommand)]
+        input: Input,
+        /// auto-pad the input to the nearest Word (8 bytes)
+        #[arg(short, long, value_enum)]
+        auto_pad: Option<Padding>,
+        /// The algorithm used to hash the input before signing.
+        #[arg(long, default_value_t = HashAlgorithm::Sha256, value_enum)]
+        hash: HashAlgorithm,
+    },
+    /// Recover the public key that created a secp256k1 signature.
+    Recover {
+        /// The signature to recover the public key from.
+        /// Both padded and unpadded signatures are accepted.
+        signature: String,
+        /// Encoding of the signature.
+        #[arg(short, long, default_value_t = Encoding::Hex, value_enum)]
+        signature_encoding: Encoding,
+        /// Require the input to be word aligned [default: true].
+        #[arg(short, long, default_value_t = true)]
+        require_aligned: bool,
+        /// The input that was signed.
+        #[command(subcommand)]
+        input: Input,
+        /// auto-pad the input to the nearest Word (8 bytes)
+        #[arg(short, long, value_enum)]
+        auto_pad: Option<Padding>,
+        /// The algorithm used to hash the input before signing.
+        #[arg(long, default_value_t = HashAlgorithm::Sha256, value_enum)]
+        hash: HashAlgorithm,
+        /// Encoding of the output public key
+        #[arg(short, long, default_value_t = Encoding::HexUpper, value_enum)]
+        output: Encoding,
+        /// Check the signature was made by the stored key pair or watch-only key at this name.
+        /// Exits with a non-zero status if it was not.
+        #[arg(short, long)]
+        name: Option<String>,
+    },
+    /// Show the signatures the wallet has made.
+    ///
+    /// Every signature is recorded with the hash of the signed input
+    /// and the sha256 hash of the signature in a hash chained audit log.
+    History {
+        /// Only show signatures made by the key pair stored under this name at the time.
+        #[arg(short, long)]
+        name: Option<String>,
+        /// Check that no entry has been changed or deleted.
+        /// Exits with a non-zero status if the log has been tampered with.
+        ///
+        /// The chain is not keyed so this only catches careless edits.
+        /// Anyone with the password can rewrite the log and recompute every hash.
+        #[arg(long)]
+        verify: bool,
+    },
+    /// Change the password of the wallet.
+    ///
+    /// The current password unlocks the wallet as usual.
+    /// The new password will be prompted for twice if not given.
+    ChangePassword {
+        /// The new password for the wallet.
+        #[arg(long)]
+        new_password: Option<String>,
+        /// Change the parameters used to derive the database key.
+        /// Unset parameters use the defaults.
+        #[command(flatten)]
+        kdf: KdfArgs,
+    },
+    /// Find the names of stored keys from a public key, its hash or a signature.
+    ///
+    /// Searches key pairs and watch-only keys.
+    /// Exits with a non-zero status if no key matches.
+    Which {
+        #[command(subcommand)]
+        key: WhichKey,
+    },
     /// Print the public key.
     PrintPubKey {
         /// Hash the public key before printing.
-        #[arg(short, long)]
+        #[arg(long)]
         hashed: bool,
         /// The name of the key to print.
         name: String,
@@ -78,6 +346,125 @@ enum Command {
     },
 }
 
+/// Argon2id parameters used to derive the database key from the password.
+#[derive(Args)]
+struct KdfArgs {
+    /// Argon2id memory cost in KiB [default: 19456, max: 1048576].
+    #[arg(long, value_parser = value_parser!(u32).range(1..=KdfParams::MAX_MEMORY_KIB as i64))]
+    kdf_memory_kib: Option<u32>,
+    /// Argon2id iterations [default: 2, max: 64].
+    #[arg(long, value_parser = value_parser!(u32).range(1..=KdfParams::MAX_ITERATIONS as i64))]
+    kdf_iterations: Option<u32>,
+    /// Argon2id parallelism [default: 1, max: 16].
+    #[arg(long, value_parser = value_parser!(u32).range(1..=KdfParams::MAX_PARALLELISM as i64))]
+    kdf_parallelism: Option<u32>,
+}
+
+impl KdfArgs {
+    /// The parameters if any were set, with the defaults for the rest.
+    fn params(&self) -> Option<KdfParams> {
+        if self.kdf_memory_kib.is_none()
+            && self.kdf_iterations.is_none()
+            && self.kdf_parallelism.is_none()
+        {
+            return None;
+        }
+        let default = KdfParams::default();
+        Some(KdfParams {
+            memory_kib: self.kdf_memory_kib.unwrap_or(default.memory_kib),
+            iterations: self.kdf_iterations.unwrap_or(default.iterations),
+            parallelism: self.kdf_parallelism.unwrap_or(default.parallelism),
+        })
+    }
+}
+
+#[derive(Subcommand)]
+enum SeedCommand {
+    /// Create the wallet seed from a new mnemonic and print the mnemonic.
+    New {
+        /// The number of words in the mnemonic.
+        #[arg(short, long, default_value_t = MnemonicLength::Words24, value_enum)]
+        words: MnemonicLength,
+        /// Optional BIP39 passphrase used with the mnemonic.
+        #[arg(long)]
+        passphrase: Option<String>,
+    },
+    /// Restore the wallet seed from a mnemonic.
+    ///
+    /// The mnemonic will be prompted for.
+    Restore {
+        /// Optional BIP39 passphrase used with the mnemonic.
+        #[arg(long)]
+        passphrase: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum WatchCommand {
+    /// Store a public key under a name.
+    ///
+    /// Both padded and unpadded public keys are accepted.
+    Add {
+        /// The name that the public key will be stored under.
+        name: String,
+        /// The public key.
+        public_key: String,
+        /// Encoding of the public key.
+        #[arg(short, long, default_value_t = Encoding::HexUpper, value_enum)]
+        encoding: Encoding,
+        /// The public key is comma separated decimal words instead of encoded bytes.
+        #[arg(short, long, conflicts_with = "encoding")]
+        words: bool,
+    },
+    /// Remove a watch-only public key.
+    Remove {
+        /// The name of the public key to remove.
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum WhichKey {
+    /// Find keys with this public key.
+    /// Both padded and unpadded public keys are accepted.
+    PublicKey {
+        /// The public key.
+        public_key: String,
+        /// Encoding of the public key.
+        #[arg(short, long, default_value_t = Encoding::HexUpper, value_enum)]
+        encoding: Encoding,
+    },
+    /// Find keys whose public key has this hash like `print-pub-key --hashed` prints.
+    Hash {
+        /// The hash of the public key.
+        hash: String,
+        /// Encoding of the hash.
+        #[arg(short, long, default_value_t = Encoding::HexUpper, value_enum)]
+        encoding: Encoding,
+    },
+    /// Find keys that made this signature.
+    Signature {
+        /// The signature.
+        /// Both padded and unpadded signatures are accepted.
+        signature: String,
+        /// Encoding of the signature.
+        #[arg(short, long, default_value_t = Encoding::Hex, value_enum)]
+        signature_encoding: Encoding,
+        /// Require the input to be word aligned [default: true].
+        #[arg(short, long, default_value_t = true)]
+        require_aligned: bool,
+        /// The input that was signed.
+        #[command(subcommand)]
+        input: Input,
+        /// auto-pad the input to the nearest Word (8 bytes)
+        #[arg(short, long, value_enum)]
+        auto_pad: Option<Padding>,
+        /// The algorithm used to hash the input before signing.
+        #[arg(long, default_value_t = HashAlgorithm::Sha256, value_enum)]
+        hash: HashAlgorithm,
+    },
+}
+
 #[derive(Subcommand)]
 enum Input {
     File {
@@ -104,31 +491,197 @@ This crate is intended for testing and educational purposes only.
 Never use this for production code or to store real funds.
 ";
 
+/// Number of times the password is prompted for before giving up.
+const PASSWORD_ATTEMPTS: usize = 3;
+
 fn main() {
     let args = Cli::parse();
     if let Err(e) = run(args) {
         eprintln!("Command failed because: {}", e);
+        std::process::exit(1);
     }
 }
 
 fn run(args: Cli) -> anyhow::Result<()> {
     eprintln!("{}", WARNING);
 
-    let pass = if let Some(password) = args.password {
-        password
-    } else {
-        rpassword::prompt_password("Enter password to unlock wallet: ")?
+    let dir = args
+        .path
+        .clone()
+        .unwrap_or_else(essential_wallet::default_dir);
+    if let Command::Init { kdf } = &args.command {
+        let pass = match &args.password {
+            Some(password) => Zeroizing::new(password.clone()),
+            None => prompt_twice("new wallet password")?,
+        };
+        Wallet::create_with_kdf(&pass, dir.clone(), kdf.params().unwrap_or_default())?;
+        println!("Created wallet at {}", dir.display());
+        return Ok(());
+    }
+    if !Wallet::exists(&dir) {
+        return Err(essential_wallet::Error::WalletNotFound(dir).into());
+    }
+
+    let mut attempts = 0;
+    let mut wallet = loop {
+        let pass = Zeroizing::new(match &args.password {
+            Some(password) => password.clone(),
+            None => rpassword::prompt_password("Enter password to unlock wallet: ")?,
+        });
+        let wallet = Wallet::open(&pass, dir.clone());
+        attempts += 1;
+        match wallet {
+            Err(essential_wallet::Error::WrongPassword)
+                if args.password.is_none() && attempts < PASSWORD_ATTEMPTS =>
+            {
+                eprintln!("Wrong password, please try again.");
+            }
+            wallet => break wallet?,
+        }
     };
 
-    // TODO: Not sure what to do for salt as it would need to be stored anyway
-    let mut wallet = args
-        .path
-        .map(|p| Wallet::new(&pass, p))
-        .unwrap_or_else(|| Wallet::with_default_path(&pass))?;
+    wallet.set_confirmation(|name, operation, hash| {
+        eprintln!(
+            "Confirm {} signature of {} with {}? (only 'yes' is accepted)",
+            operation,
+            essential_signer::encode_slice(hash, Encoding::Hex).unwrap_or_default(),
+            name
+        );
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input).is_ok() && input.trim() == "yes"
+    });
 
     match args.command {
-        Command::Generate { name, scheme } => {
-            wallet.new_key_pair(&name, scheme)?;
+        Command::Init { .. } => unreachable!("The wallet is created before it is opened"),
+        Command::Generate {
+            name,
+            scheme,
+            mnemonic,
+            passphrase,
+            derive,
+            path,
+        } => match mnemonic {
+            Some(length) => {
+                let passphrase = Zeroizing::new(passphrase.unwrap_or_default());
+                let phrase =
+                    wallet.new_key_pair_with_mnemonic(&name, scheme, length, &passphrase)?;
+                eprintln!("Write down this mnemonic. It can be used to restore the key:");
+                println!("{}", *phrase);
+            }
+            None if derive || path.is_some() => {
+                let path = wallet.new_derived_key_pair(&name, scheme, path)?;
+                println!("Derived {} at {}", name, path);
+            }
+            None => wallet.new_key_pair(&name, scheme)?,
+        },
+        Command::Seed { command } => match command {
+            SeedCommand::New { words, passphrase } => {
+                let passphrase = Zeroizing::new(passphrase.unwrap_or_default());
+                let phrase = wallet.create_seed(words, &passphrase)?;
+                eprintln!("Write down this mnemonic. It can be used to restore the seed:");
+                println!("{}", *phrase);
+            }
+            SeedCommand::Restore { passphrase } => {
+                let passphrase = Zeroizing::new(passphrase.unwrap_or_default());
+                let phrase = Zeroizing::new(rpassword::prompt_password("Enter mnemonic: ")?);
+                wallet.restore_seed(&phrase, &passphrase)?;
+            }
+        },
+        Command::Watch { command } => match command {
+            WatchCommand::Add {
+                name,
+                public_key,
+                encoding,
+                words,
+            } => {
+                let public_key = if words {
+                    essential_signer::public_key_from_words(&parse_words(&public_key)?)?
+                } else {
+                    decode_public_key(public_key, encoding)?
+                };
+                wallet.add_watch_only_key(&name, &public_key)?;
+                println!("Watching {} key {}", Scheme::from(&public_key), name);
+            }
+            WatchCommand::Remove { name } => wallet.delete_watch_only_key(&name)?,
+        },
+        Command::Restore {
+            name,
+            scheme,
+            passphrase,
+        } => {
+            let passphrase = Zeroizing::new(passphrase.unwrap_or_default());
+            let phrase = Zeroizing::new(rpassword::prompt_password("Enter mnemonic: ")?);
+            wallet.restore_key_pair(&name, scheme, &phrase, &passphrase)?;
+        }
+        Command::Import {
+            name,
+            scheme,
+            file,
+            encoding,
+            keystore,
+            passphrase,
+        } => {
+            let scheme = match file {
+                Some(path) if keystore => {
+                    let json = std::fs::read_to_string(path)?;
+                    let passphrase = Zeroizing::new(match passphrase {
+                        Some(passphrase) => passphrase,
+                        None => rpassword::prompt_password("Enter keystore passphrase: ")?,
+                    });
+                    wallet.import_keystore(&name, &json, &passphrase)?;
+                    Scheme::Secp256k1
+                }
+                Some(path) => {
+                    let data = Zeroizing::new(std::fs::read(path)?);
+                    let passphrase = passphrase.map(Zeroizing::new);
+                    match wallet.import_key_with_passphrase(
+                        &name,
+                        &data,
+                        encoding,
+                        scheme,
+                        passphrase.as_deref().map(String::as_str),
+                    ) {
+                        Err(essential_wallet::Error::MissingPassphrase) => {
+                            let passphrase = Zeroizing::new(rpassword::prompt_password(
+                                "Enter key passphrase: ",
+                            )?);
+                            wallet.import_key_with_passphrase(
+                                &name,
+                                &data,
+                                encoding,
+                                scheme,
+                                Some(&passphrase),
+                            )?
+                        }
+                        scheme => scheme?,
+                    }
+                }
+                None => {
+                    let data = Zeroizing::new(rpassword::prompt_password("Enter private key: ")?);
+                    wallet.import_key(&name, data.as_bytes(), encoding, scheme)?
+                }
+            };
+            println!("Imported {} key {}", scheme, name);
+        }
+        Command::Export {
+            name,
+            format,
+            output,
+            passphrase,
+        } => {
+            let passphrase = match passphrase {
+                Some(passphrase) => Some(Zeroizing::new(passphrase)),
+                None if format.requires_passphrase() => Some(prompt_twice("export passphrase")?),
+                None => None,
+            };
+            let passphrase = passphrase.as_deref().map(String::as_str);
+            match output {
+                Some(path) => {
+                    wallet.export_key_to_file(&name, format, passphrase, &path)?;
+                    println!("Exported {} to {}", name, path.display());
+                }
+                None => println!("{}", *wallet.export_key(&name, format, passphrase)?),
+            }
         }
         Command::Delete { name } => {
             println!(
@@ -140,11 +693,128 @@ fn run(args: Cli) -> anyhow::Result<()> {
             ensure!(input.trim() == "yes", "Aborted");
             wallet.delete_key_pair(&name)?;
         }
-        Command::List => {
-            let names = wallet.list_names()?;
+        Command::Rename { name, new_name } => {
+            wallet.rename_key_pair(&name, &new_name)?;
+            println!("Renamed {} to {}", name, new_name);
+        }
+        Command::Label {
+            name,
+            description,
+            clear_description,
+            tags,
+        } => {
+            if description.is_some() || clear_description {
+                wallet.set_description(&name, description.as_deref())?;
+            }
+            if let Some(tags) = tags {
+                let tags: Vec<String> = tags.into_iter().filter(|t| !t.is_empty()).collect();
+                wallet.set_tags(&name, &tags)?;
+            }
+        }
+        Command::Policy {
+            name,
+            allow,
+            max_input_size,
+            expires,
+            expires_in,
+            confirm,
+            clear,
+        } => {
+            let policy = SigningPolicy {
+                allowed_operations: allow,
+                max_input_size,
+                expires_at: expires.or_else(|| expires_in.map(|d| SystemTime::now() + d)),
+                require_confirmation: confirm,
+            };
+            if clear || !policy.is_unrestricted() {
+                wallet.set_policy(&name, &policy)?;
+            }
+            let policy = wallet.key_info(&name)?.policy;
+            println!(
+                "Allowed operations: {}",
+                match policy.allowed_operations {
+                    Some(ops) => ops
+                        .iter()
+                        .map(|op| op.to_string())
+                        .collect::<Vec<_>>()
+                        .join(","),
+                    None => "any".to_string(),
+                }
+            );
+            println!(
+                "Max input size: {}",
+                match policy.max_input_size {
+                    Some(max) => format!("{} bytes", max),
+                    None => "unlimited".to_string(),
+                }
+            );
+            println!(
+                "Expires: {}",
+                match policy.expires_at {
+                    Some(_) => format_time(policy.expires_at),
+                    None => "never".to_string(),
+                }
+            );
+            println!(
+                "Requires confirmation: {}",
+                if policy.require_confirmation {
+                    "yes"
+                } else {
+                    "no"
+                }
+            );
+        }
+        Command::List { long: false } => {
+            let names = wallet.list_derivation_paths()?;
             println!("Stored Accounts:");
-            for name in names {
-                println!("{}", name);
+            for (name, path) in names {
+                match path {
+                    Some(path) => println!("{} ({})", name, path),
+                    None => println!("{}", name),
+                }
+            }
+            let watched = wallet.list_watch_only_keys()?;
+            if !watched.is_empty() {
+                println!("Watch-only Accounts:");
+                for key in watched {
+                    println!("{} ({})", key.name, Scheme::from(&key.public_key));
+                }
+            }
+        }
+        Command::List { long: true } => {
+            println!("NAME\tSCHEME\tPATH\tCREATED\tUPDATED\tLAST USED\tTAGS\tDESCRIPTION");
+            for key in wallet.list_keys()? {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    key.name,
+                    key.scheme,
+                    key.derivation_path
+                        .map(|p| p.to_string())
+                        .unwrap_or_else(|| "-".to_string()),
+                    format_time(key.created_at),
+                    format_time(key.updated_at),
+                    format_time(key.last_used_at),
+                    if key.tags.is_empty() {
+                        "-".to_string()
+                    } else {
+                        key.tags.join(",")
+                    },
+                    key.description.unwrap_or_else(|| "-".to_string()),
+                );
+            }
+            let watched = wallet.list_watch_only_keys()?;
+            if !watched.is_empty() {
+                println!();
+                println!("WATCH-ONLY NAME\tSCHEME\tCREATED\tPUBLIC KEY");
+                for key in watched {
+                    println!(
+                        "{}\t{}\t{}\t{}",
+                        key.name,
+                        Scheme::from(&key.public_key),
+                        format_time(key.created_at),
+                        encode_public_key(&key.public_key, Encoding::HexUpper)?,
+                    );
+                }
             }
         }
         Command::Sign {
@@ -152,24 +822,11 @@ fn run(args: Cli) -> anyhow::Result<()> {
             require_aligned,
             input,
             auto_pad,
+            hash,
             pad_signature,
             output,
         } => {
-            let data = match input {
-                Input::File { path } => read_file(&path)?,
-                Input::Data { data, encoding } => decode_str(data, encoding)?,
-            };
-            let sig = if require_aligned {
-                match auto_pad {
-                    Some(padding) => wallet.sign_bytes_with_padding(data, padding, &name)?,
-                    None => wallet.sign_aligned_bytes(&data, &name)?,
-                }
-            } else {
-                match auto_pad {
-                    Some(padding) => wallet.sign_bytes_with_padding(data, padding, &name)?,
-                    None => wallet.sign_bytes_unchecked(&data, &name)?,
-                }
-            };
+            let sig = sign_input(&mut wallet, &name, input, require_aligned, auto_pad, hash)?;
             output_signature(&sig, pad_signature, output)?;
         }
         Command::SignContract { name, path, output } => {
@@ -181,47 +838,272 @@ fn run(args: Cli) -> anyhow::Result<()> {
             let sig = essential_signer::encode_str(sig, output)?;
             println!("{}", sig);
         }
+        Command::Verify {
+            signature,
+            signature_encoding,
+            name,
+            public_key,
+            public_key_encoding,
+            require_aligned,
+            input,
+            auto_pad,
+            hash,
+        } => {
+            let sig = decode_signature(signature, signature_encoding)?;
+            let pub_key = match (name, public_key) {
+                (Some(name), _) => wallet.lookup_public_key(&name)?,
+                (None, Some(public_key)) => decode_public_key(public_key, public_key_encoding)?,
+                (None, None) => anyhow::bail!("Either a name or a public key is required"),
+            };
+            let hash = hash_input(input, require_aligned, auto_pad, hash)?;
+            essential_signer::verify_hash(hash, &sig, &pub_key)?;
+            println!("Signature is valid");
+        }
+        Command::Recover {
+            signature,
+            signature_encoding,
+            require_aligned,
+            input,
+            auto_pad,
+            hash,
+            output,
+            name,
+        } => {
+            let sig = decode_signature(signature, signature_encoding)?;
+            let hash = hash_input(input, require_aligned, auto_pad, hash)?;
+            let pub_key = essential_signer::recover_hash(hash, &sig)?;
+            println!("{}", encode_public_key(&pub_key, output)?);
+            if let Some(name) = name {
+                ensure!(
+                    wallet.lookup_public_key(&name)? == pub_key,
+                    "The signature was not made by {}",
+                    name
+                );
+                println!("Signature was made by {}", name);
+            }
+        }
+        Command::History { name, verify } => {
+            println!("ID\tTIME\tNAME\tSCHEME\tOPERATION\tINPUT HASH\tSIGNATURE HASH");
+            for entry in wallet.audit_log(name.as_deref())? {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    entry.id,
+                    format_time(entry.time),
+                    entry.name,
+                    entry.scheme,
+                    entry.operation,
+                    essential_signer::encode_slice(&entry.input_hash, Encoding::Hex)?,
+                    essential_signer::encode_slice(&entry.signature_hash, Encoding::Hex)?,
+                );
+            }
+            if verify {
+                let entries = wallet.verify_audit_log()?;
+                println!("Audit log is intact ({} entries)", entries);
+            }
+        }
+        Command::ChangePassword { new_password, kdf } => {
+            let new_password = match new_password {
+                Some(password) => Zeroizing::new(password),
+                None => prompt_twice("new wallet password")?,
+            };
+            match kdf.params() {
+                Some(params) => wallet.change_password_with_kdf(&new_password, params)?,
+                None => wallet.change_password(&new_password)?,
+            }
+            println!("Wallet password changed");
+        }
+        Command::Which { key } => {
+            let names = match key {
+                WhichKey::PublicKey {
+                    public_key,
+                    encoding,
+                } => wallet.find_by_public_key(&decode_public_key(public_key, encoding)?)?,
+                WhichKey::Hash { hash, encoding } => {
+                    let hash = decode_str(hash, encoding)?;
+                    let hash: Hash = hash.try_into().map_err(|hash: Vec<u8>| {
+                        anyhow::anyhow!("Expected a 32 byte hash but got {} bytes", hash.len())
+                    })?;
+                    wallet.find_by_pubkey_hash(&hash)?
+                }
+                WhichKey::Signature {
+                    signature,
+                    signature_encoding,
+                    require_aligned,
+                    input,
+                    auto_pad,
+                    hash,
+                } => {
+                    let sig = decode_signature(signature, signature_encoding)?;
+                    let hash = hash_input(input, require_aligned, auto_pad, hash)?;
+                    wallet.find_by_signature(hash, &sig)?
+                }
+            };
+            ensure!(!names.is_empty(), "No stored key matches");
+            for name in names {
+                println!("{}", name);
+            }
+        }
         Command::PrintPubKey { name, hashed } => {
             let pub_key = wallet.get_public_key(&name)?;
             if hashed {
                 println!(
                     "{}",
-                    essential_signer::encode_str(
-                        essential_signer::hash_words(&essential_signer::public_key_to_words(
-                            &pub_key
-                        ))
-                        .to_vec(),
+                    essential_signer::encode_slice(
+                        &essential_wallet::public_key_hash(&pub_key),
                         Encoding::HexUpper
                     )?
                 );
             } else {
-                println!(
-                    "{}",
-                    essential_signer::encode_str(
-                        essential_signer::public_key_to_words(&pub_key)
-                            .into_iter()
-                            .flat_map(bytes_from_word)
-                            .collect(),
-                        Encoding::HexUpper
-                    )?
-                );
+                println!("{}", encode_public_key(&pub_key, Encoding::HexUpper)?);
             }
         }
         Command::PrintPrivKey { name } => {
             let priv_key = wallet.get_private_key(&name)?;
-            let bytes = match priv_key {
-                essential_signer::Key::Secp256k1(secret_key) => secret_key.secret_bytes().to_vec(),
-                essential_signer::Key::Ed25519(secret_key) => secret_key.to_vec(),
-            };
-            println!(
-                "{}",
-                essential_signer::encode_str(bytes, Encoding::HexUpper)?
-            );
+            let encoded = Zeroizing::new(essential_signer::encode_slice(
+                priv_key.secret_bytes().expose(),
+                Encoding::HexUpper,
+            )?);
+            println!("{}", *encoded);
         }
     }
     Ok(())
 }
 
+/// Format a time from the wallet or `-` if it is not known.
+fn format_time(time: Option<std::time::SystemTime>) -> String {
+    time.map(|t| humantime::format_rfc3339_seconds(t).to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Prompt for a new secret twice to catch typos.
+fn prompt_twice(what: &str) -> anyhow::Result<Zeroizing<String>> {
+    let secret = Zeroizing::new(rpassword::prompt_password(format!("Enter {}: ", what))?);
+    let confirm = Zeroizing::new(rpassword::prompt_password(format!("Confirm {}: ", what))?);
+    ensure!(secret == confirm, "The {}s do not match", what);
+    Ok(secret)
+}
+
+/// Sign the input with the key pair stored at this name, padding it if requested.
+///
+/// Files are hashed as they are read so they are never fully held in memory.
+/// The key pair's policy is checked before the input is read.
+/// Returns an error if alignment is required and the input is not padded or word aligned.
+fn sign_input(
+    wallet: &mut Wallet,
+    name: &str,
+    input: Input,
+    require_aligned: bool,
+    auto_pad: Option<Padding>,
+    hasher: HashAlgorithm,
+) -> anyhow::Result<Signature> {
+    let sig = match input {
+        Input::File { path } => {
+            let reader = std::fs::File::open(path)?;
+            let len = reader.metadata()?.len();
+            if auto_pad.is_none() && require_aligned && len % 8 != 0 {
+                return Err(essential_signer::Error::NotWordAligned.into());
+            }
+            wallet.sign_reader(reader, len, auto_pad, hasher, name)?
+        }
+        Input::Data { data, encoding } => {
+            let data = decode_str(data, encoding)?;
+            match auto_pad {
+                Some(padding) => {
+                    wallet.sign_bytes_with_padding_and_hasher(data, padding, hasher, name)?
+                }
+                None if require_aligned => {
+                    wallet.sign_aligned_bytes_with_hasher(&data, hasher, name)?
+                }
+                None => wallet.sign_bytes_unchecked_with_hasher(&data, hasher, name)?,
+            }
+        }
+    };
+    Ok(sig)
+}
+
+/// Pad the input if requested and hash it.
+///
+/// Files are hashed as they are read so they are never fully held in memory.
+/// Returns an error if alignment is required and the input is not padded or word aligned.
+fn hash_input(
+    input: Input,
+    require_aligned: bool,
+    auto_pad: Option<Padding>,
+    hasher: HashAlgorithm,
+) -> Result<Hash, essential_signer::Error> {
+    match input {
+        Input::File { path } => {
+            let reader = std::fs::File::open(path)?;
+            let len = reader.metadata()?.len();
+            match auto_pad {
+                Some(padding) => {
+                    essential_signer::hash_reader_with_padding(reader, len, padding, hasher)
+                }
+                None if require_aligned => essential_signer::hash_aligned_reader(reader, hasher),
+                None => essential_signer::hash_reader(reader, hasher),
+            }
+        }
+        Input::Data { data, encoding } => {
+            let data = decode_str(data, encoding)?;
+            let data = match auto_pad {
+                Some(padding) => essential_signer::align_to_word(data, padding),
+                None if require_aligned && !essential_signer::is_word_aligned(&data) => {
+                    return Err(essential_signer::Error::NotWordAligned)
+                }
+                None => data,
+            };
+            Ok(hasher.hash(&data))
+        }
+    }
+}
+
+/// Decode a signature that is either padded to be word aligned or unpadded.
+fn decode_signature(sig: String, encoding: Encoding) -> Result<Signature, essential_signer::Error> {
+    let bytes = decode_str(sig, encoding)?;
+    if bytes.len() == 72 {
+        essential_signer::signature_from_aligned_bytes(&bytes)
+    } else {
+        essential_signer::signature_from_bytes(&bytes)
+    }
+}
+
+/// Decode a public key that is either padded to be word aligned
+/// like `print-pub-key` outputs or unpadded.
+fn decode_public_key(
+    key: String,
+    encoding: Encoding,
+) -> Result<PublicKey, essential_signer::Error> {
+    let bytes = decode_str(key, encoding)?;
+    if bytes.len() == 40 {
+        essential_signer::public_key_from_aligned_bytes(&bytes)
+    } else {
+        essential_signer::public_key_from_bytes(&bytes)
+    }
+}
+
+/// Encode a public key padded to be word aligned.
+fn encode_public_key(
+    key: &PublicKey,
+    encoding: Encoding,
+) -> Result<String, essential_signer::Error> {
+    essential_signer::encode_str(
+        essential_signer::public_key_to_words(key)
+            .into_iter()
+            .flat_map(bytes_from_word)
+            .collect(),
+        encoding,
+    )
+}
+
+/// Parse comma or whitespace separated words.
+fn parse_words(words: &str) -> anyhow::Result<Vec<Word>> {
+    Ok(words
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|w| !w.is_empty())
+        .map(str::parse)
+        .collect::<Result<_, _>>()?)
+}
+
 fn output_signature(sig: &Signature, pad_signature: bool, output: Encoding) -> anyhow::Result<()> {
     let sig = if pad_signature {
         essential_signer::signature_to_aligned_bytes(sig)
@@ -0,0 +1,106 @@
+//! BIP39 mnemonic backups for keys.
+//!
+//! A mnemonic and optional passphrase are turned into a seed.
+//! The key is derived from the seed at the first default path,
+//! `m/44'/60'/0'/0/0` with BIP32 for secp256k1 and `m/44'/60'/0'/0'/0'`
+//! with SLIP-0010 for ed25519.
+//! Other BIP39 wallets restore the same secp256k1 key as this is the Ethereum path.
+//! There is no common path for ed25519 keys on Essential,
+//! so only this wallet is known to restore them.
+
+use clap::ValueEnum;
+use essential_signer::{Key, SecretBytes};
+use rand::{RngCore, SeedableRng};
+use zeroize::Zeroizing;
+
+use crate::{
+    hd::{self, DerivationPath},
+    Error, Scheme,
+};
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// The number of words in a mnemonic.
+pub enum MnemonicLength {
+    /// 12 words (128 bits of entropy).
+    #[value(name = "12")]
+    Words12,
+    /// 24 words (256 bits of entropy).
+    #[default]
+    #[value(name = "24")]
+    Words24,
+}
+
+impl MnemonicLength {
+    fn entropy_len(&self) -> usize {
+        match self {
+            MnemonicLength::Words12 => 16,
+            MnemonicLength::Words24 => 32,
+        }
+    }
+}
+
+/// Generate a new random mnemonic.
+pub(crate) fn generate(length: MnemonicLength) -> Result<bip39::Mnemonic, Error> {
+    let mut rng = rand::rngs::StdRng::from_entropy();
+    let mut entropy = Zeroizing::new(vec![0u8; length.entropy_len()]);
+    rng.fill_bytes(&mut entropy);
+    Ok(bip39::Mnemonic::from_entropy(&entropy)?)
+}
+
+/// Parse an english mnemonic phrase.
+pub(crate) fn parse(phrase: &str) -> Result<bip39::Mnemonic, Error> {
+    Ok(bip39::Mnemonic::parse_in(bip39::Language::English, phrase)?)
+}
+
+/// The BIP39 seed of the mnemonic and passphrase.
+pub(crate) fn to_seed(mnemonic: &bip39::Mnemonic, passphrase: &str) -> SecretBytes {
+    let seed = Zeroizing::new(mnemonic.to_seed(passphrase));
+    SecretBytes::from(seed.as_slice())
+}
+
+/// Derive the key for this scheme from the mnemonic and passphrase
+/// at the first default path.
+pub(crate) fn to_key(
+    mnemonic: &bip39::Mnemonic,
+    passphrase: &str,
+    scheme: Scheme,
+) -> Result<Key, Error> {
+    let seed = to_seed(mnemonic, passphrase);
+    hd::derive(
+        seed.expose(),
+        &DerivationPath::default_for(scheme, 0),
+        scheme,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHRASE: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn matches_other_wallets() {
+        // The first account of this mnemonic in Ethereum wallets.
+        let key = to_key(&parse(PHRASE).unwrap(), "", Scheme::Secp256k1).unwrap();
+        assert_eq!(
+            hex::encode(key.secret_bytes().expose()),
+            "1ab42cc412b618bdea3a599e3c9bae199ebf030895b039e9db1e30dafb12b727"
+        );
+    }
+
+    #[test]
+    fn passphrase_changes_key() {
+        let mnemonic = generate(MnemonicLength::Words12).unwrap();
+        assert_eq!(parse(&mnemonic.to_string()).unwrap(), mnemonic);
+        for scheme in [Scheme::Secp256k1, Scheme::Ed25519] {
+            let key = |passphrase| {
+                let key = to_key(&mnemonic, passphrase, scheme).unwrap();
+                key.secret_bytes().expose().to_vec()
+            };
+            assert_eq!(key("a"), key("a"));
+            assert_ne!(key("a"), key("b"));
+        }
+    }
+}
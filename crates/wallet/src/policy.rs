@@ -0,0 +1,86 @@
+//! Restrictions on how a key pair may sign.
+//!
+//! Policies are stored with the key and checked before every signature.
+
+use std::time::SystemTime;
+
+use essential_types::Hash;
+
+use crate::SignOperation;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// The signing policy of a key pair.
+///
+/// The default policy allows the key pair to sign anything.
+pub struct SigningPolicy {
+    /// The operations the key pair may sign with or `None` for any operation.
+    pub allowed_operations: Option<Vec<SignOperation>>,
+    /// The largest input in bytes the key pair may sign.
+    ///
+    /// This is the size of the data that is hashed,
+    /// which is the postcard serialization for contracts and postcard data,
+    /// the padded bytes for padded data, 8 bytes per word for words
+    /// and 32 bytes for `Wallet::sign_hash`.
+    /// The CLI `sign` command signs bytes so this limits the size of the file or data.
+    pub max_input_size: Option<u64>,
+    /// When the key pair stops being allowed to sign.
+    pub expires_at: Option<SystemTime>,
+    /// Whether each signature must be confirmed.
+    /// See `Wallet::set_confirmation`.
+    pub require_confirmation: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+/// Why a signing policy did not allow a signature.
+pub enum PolicyViolation {
+    /// The policy does not allow this operation.
+    #[error("{0} signing is not allowed")]
+    OperationNotAllowed(SignOperation),
+    /// The input is larger than the policy allows.
+    #[error("the input is {0} bytes but at most {1} bytes are allowed")]
+    InputTooLarge(u64, u64),
+    /// The key pair has expired.
+    #[error("the key expired at {}", humantime::format_rfc3339_seconds(*.0))]
+    Expired(SystemTime),
+    /// The signature was not confirmed.
+    #[error("the signature was not confirmed")]
+    NotConfirmed,
+}
+
+/// Asks whether a signature with a key pair that requires confirmation should be made.
+///
+/// Called with the name of the key pair, the operation and the hash that will be signed.
+pub type Confirmation = Box<dyn FnMut(&str, SignOperation, &Hash) -> bool + Send>;
+
+impl SigningPolicy {
+    /// Whether the policy restricts anything.
+    pub fn is_unrestricted(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check the policy allows signing this input with this operation now.
+    ///
+    /// Confirmation is not checked here as it must be asked for last.
+    pub(crate) fn check(
+        &self,
+        operation: SignOperation,
+        input_size: u64,
+    ) -> Result<(), PolicyViolation> {
+        if let Some(allowed) = &self.allowed_operations {
+            if !allowed.contains(&operation) {
+                return Err(PolicyViolation::OperationNotAllowed(operation));
+            }
+        }
+        if let Some(max) = self.max_input_size {
+            if input_size > max {
+                return Err(PolicyViolation::InputTooLarge(input_size, max));
+            }
+        }
+        if let Some(expires_at) = self.expires_at {
+            if SystemTime::now() >= expires_at {
+                return Err(PolicyViolation::Expired(expires_at));
+            }
+        }
+        Ok(())
+    }
+}
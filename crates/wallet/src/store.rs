@@ -1,68 +1,636 @@
 use std::{path::Path, str::FromStr};
 
-use rusqlite::{params, Connection};
+use essential_signer::{PublicKey, SecretBytes};
+use essential_types::Hash;
+use rusqlite::{
+    params, Connection, ErrorCode, OptionalExtension, Transaction, TransactionBehavior,
+};
+use zeroize::Zeroizing;
 
-use crate::Scheme;
+use crate::{
+    audit::{self, AuditEntry, SignOperation},
+    info::{self, KeyInfo, WatchOnlyKey},
+    Error, Scheme, SigningPolicy,
+};
 
 pub struct Store {
     conn: Connection,
 }
 
+/// The key used to encrypt the database.
+pub enum DbKey<'a> {
+    /// Wallets created before the KDF header used the hex encoded
+    /// password as a sqlcipher passphrase.
+    Legacy(&'a str),
+    /// A raw key derived from the password which skips the sqlcipher KDF.
+    Raw(&'a [u8; 32]),
+}
+
+impl DbKey<'_> {
+    fn pragma_value(&self) -> Zeroizing<String> {
+        match self {
+            DbKey::Legacy(password) => Zeroizing::new(hex::encode(password.as_bytes())),
+            DbKey::Raw(key) => Zeroizing::new(format!("x'{}'", hex::encode(key))),
+        }
+    }
+}
+
 impl Store {
-    pub fn new(password: &str, path: &Path) -> anyhow::Result<Self> {
+    pub fn new(key: &DbKey, path: &Path) -> Result<Self, Error> {
         let conn = Connection::open(path)?;
-        let conn = unlock(password.as_bytes(), conn)?;
+        let conn = unlock(key, conn)?;
         Ok(Self { conn })
     }
-    pub fn set_secret(&self, name: &str, scheme: Scheme, secret: &[u8]) -> anyhow::Result<()> {
-        self.conn.execute(
-            "INSERT OR ABORT INTO names (name, scheme, secret) VALUES (?, ?, ?)",
-            params![name, scheme.to_string(), secret],
+
+    /// Re-encrypt the database with a new key.
+    ///
+    /// sqlcipher rewrites every page in a single transaction
+    /// so the database is never left with a mix of keys.
+    pub fn rekey(&self, key: &DbKey) -> Result<(), Error> {
+        self.conn
+            .pragma_update(None, "rekey", key.pragma_value().as_str())?;
+        Ok(())
+    }
+
+    pub fn set_secret(
+        &self,
+        name: &str,
+        scheme: Scheme,
+        secret: &SecretBytes,
+    ) -> Result<(), Error> {
+        self.insert_secret(name, scheme, secret, None)
+    }
+
+    pub fn set_derived_secret(
+        &self,
+        name: &str,
+        scheme: Scheme,
+        secret: &SecretBytes,
+        path: &str,
+    ) -> Result<(), Error> {
+        self.insert_secret(name, scheme, secret, Some(path))
+    }
+
+    fn insert_secret(
+        &self,
+        name: &str,
+        scheme: Scheme,
+        secret: &SecretBytes,
+        path: Option<&str>,
+    ) -> Result<(), Error> {
+        let tx = self.write_transaction()?;
+        ensure_name_free(&tx, name)?;
+        let now = info::now();
+        tx.execute(
+            "INSERT OR ABORT INTO names
+                    (name, scheme, secret, public_key, path, created_at, updated_at)
+                    VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![
+                name,
+                scheme.to_string(),
+                secret.expose(),
+                public_key_bytes(scheme, secret),
+                path,
+                now,
+                now
+            ],
+        )
+        .map_err(|e| match e.sqlite_error_code() {
+            Some(ErrorCode::ConstraintViolation) => Error::DuplicateKeyName(name.to_string()),
+            _ => e.into(),
+        })?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get_secret(&self, name: &str) -> Result<(SecretBytes, Scheme), Error> {
+        let (sec, sch) = self
+            .conn
+            .query_row(
+                "SELECT secret, scheme FROM names WHERE name = ? LIMIT 1",
+                params![name],
+                |row| {
+                    let s = SecretBytes::new(row.get(0)?);
+                    let scheme = row.get::<_, String>(1)?;
+                    let scheme = Scheme::from_str(&scheme);
+
+                    Ok((s, scheme))
+                },
+            )
+            .optional()?
+            .ok_or_else(|| Error::KeyNotFound(name.to_string()))?;
+        Ok((sec, sch?))
+    }
+
+    pub fn delete_secret(&self, name: &str) -> Result<(), Error> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM names WHERE name = ?", params![name])?;
+        if deleted == 0 {
+            return Err(Error::KeyNotFound(name.to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn rename_secret(&self, name: &str, new_name: &str) -> Result<(), Error> {
+        let tx = self.write_transaction()?;
+        ensure_name_free(&tx, new_name)?;
+        let renamed = tx
+            .execute(
+                "UPDATE OR ABORT names SET name = ?, updated_at = ? WHERE name = ?",
+                params![new_name, info::now(), name],
+            )
+            .map_err(|e| match e.sqlite_error_code() {
+                Some(ErrorCode::ConstraintViolation) => {
+                    Error::DuplicateKeyName(new_name.to_string())
+                }
+                _ => e.into(),
+            })?;
+        if renamed == 0 {
+            return Err(Error::KeyNotFound(name.to_string()));
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn set_description(&self, name: &str, description: Option<&str>) -> Result<(), Error> {
+        self.update(
+            name,
+            "UPDATE names SET description = ?, updated_at = ? WHERE name = ?",
+            params![description, info::now(), name],
+        )
+    }
+
+    pub fn set_tags(&self, name: &str, tags: &[String]) -> Result<(), Error> {
+        let tags = serde_json::to_string(tags).expect("Tags serialize to JSON");
+        self.update(
+            name,
+            "UPDATE names SET tags = ?, updated_at = ? WHERE name = ?",
+            params![tags, info::now(), name],
+        )
+    }
+
+    pub fn set_policy(&self, name: &str, policy: &SigningPolicy) -> Result<(), Error> {
+        let allowed_operations = policy.allowed_operations.as_ref().map(|ops| {
+            let ops: Vec<String> = ops.iter().map(|op| op.to_string()).collect();
+            serde_json::to_string(&ops).expect("Operations serialize to JSON")
+        });
+        self.update(
+            name,
+            "UPDATE names SET allowed_operations = ?, max_input_size = ?, expires_at = ?,
+                require_confirmation = ?, updated_at = ? WHERE name = ?",
+            params![
+                allowed_operations,
+                policy.max_input_size,
+                policy.expires_at.map(info::to_unix),
+                policy.require_confirmation,
+                info::now(),
+                name
+            ],
+        )
+    }
+
+    /// Record that the key pair at this name made a signature.
+    ///
+    /// Sets when the key was last used and appends an entry to the audit log.
+    pub fn record_signature(
+        &self,
+        name: &str,
+        scheme: Scheme,
+        operation: SignOperation,
+        input_hash: &Hash,
+        signature_hash: &Hash,
+    ) -> Result<(), Error> {
+        // The write lock is held from the start so no other connection
+        // can append between reading the last hash and inserting the entry.
+        let tx = self.write_transaction()?;
+        let now = info::now();
+        let used = tx.execute(
+            "UPDATE names SET last_used_at = ? WHERE name = ?",
+            params![now, name],
         )?;
+        if used == 0 {
+            return Err(Error::KeyNotFound(name.to_string()));
+        }
+        // The next id follows the highest id ever used so deleted entries leave a gap.
+        let id = audit_sequence(&tx)? + 1;
+        let prev_hash: Vec<u8> = tx
+            .query_row(
+                "SELECT hash FROM audit ORDER BY id DESC LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?
+            .unwrap_or_else(|| audit::GENESIS.to_vec());
+        let scheme = scheme.to_string();
+        let operation = operation.to_string();
+        let record = audit::Record {
+            id,
+            timestamp: now,
+            name,
+            scheme: &scheme,
+            operation: &operation,
+            input_hash,
+            signature_hash,
+        };
+        tx.execute(
+            "INSERT INTO audit
+                (id, timestamp, name, scheme, operation, input_hash, signature_hash, prev_hash, hash)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                id,
+                now,
+                name,
+                scheme,
+                operation,
+                input_hash,
+                signature_hash,
+                prev_hash,
+                record.hash(&prev_hash),
+            ],
+        )?;
+        tx.commit()?;
         Ok(())
     }
 
-    pub fn get_secret(&self, name: &str) -> anyhow::Result<(Vec<u8>, Scheme)> {
-        let (sec, sch) = self.conn.query_row(
-            "SELECT secret, scheme FROM names WHERE name = ? LIMIT 1",
-            params![name],
-            |row| {
-                let s = row.get(0)?;
-                let scheme = row.get::<_, String>(1)?;
-                let scheme = Scheme::from_str(&scheme);
+    /// The audit log in the order the signatures were made,
+    /// optionally only for the key pair that was stored under this name.
+    pub fn audit_log(&self, name: Option<&str>) -> Result<Vec<AuditEntry>, Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, timestamp, name, scheme, operation, input_hash, signature_hash
+                FROM audit WHERE ?1 IS NULL OR name = ?1 ORDER BY id",
+        )?;
+        let rows = stmt.query_and_then(params![name], audit_entry)?;
+        let mut entries = Vec::new();
+        for row in rows {
+            entries.push(row?);
+        }
+        Ok(entries)
+    }
 
-                Ok((s, scheme))
-            },
+    /// Check every entry of the audit log is present, unchanged and chained to the one before.
+    ///
+    /// Returns the number of entries.
+    pub fn verify_audit_log(&self) -> Result<u64, Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, timestamp, name, scheme, operation, input_hash, signature_hash, prev_hash, hash
+                FROM audit ORDER BY id",
         )?;
-        Ok((sec, sch?))
+        let mut rows = stmt.query([])?;
+        let mut expected_id = 1;
+        let mut prev_hash = audit::GENESIS.to_vec();
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            let name: String = row.get(2)?;
+            let scheme: String = row.get(3)?;
+            let operation: String = row.get(4)?;
+            let input_hash: Vec<u8> = row.get(5)?;
+            let signature_hash: Vec<u8> = row.get(6)?;
+            let stored_prev_hash: Vec<u8> = row.get(7)?;
+            let hash: Vec<u8> = row.get(8)?;
+            let record = audit::Record {
+                id,
+                timestamp: row.get(1)?,
+                name: &name,
+                scheme: &scheme,
+                operation: &operation,
+                input_hash: &input_hash,
+                signature_hash: &signature_hash,
+            };
+            if id != expected_id
+                || stored_prev_hash != prev_hash
+                || record.hash(&prev_hash) != hash.as_slice()
+            {
+                return Err(Error::AuditLogTampered(expected_id as u64));
+            }
+            expected_id += 1;
+            prev_hash = hash;
+        }
+        // Entries deleted from the end leave the sequence ahead of the last id.
+        let entries = expected_id - 1;
+        if audit_sequence(&self.conn)? != entries {
+            return Err(Error::AuditLogTampered(expected_id as u64));
+        }
+        Ok(entries as u64)
     }
 
-    pub fn delete_secret(&self, name: &str) -> anyhow::Result<()> {
+    fn update(&self, name: &str, sql: &str, params: impl rusqlite::Params) -> Result<(), Error> {
+        if self.conn.execute(sql, params)? == 0 {
+            return Err(Error::KeyNotFound(name.to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn key_info(&self, name: &str) -> Result<KeyInfo, Error> {
         self.conn
-            .execute("DELETE FROM names WHERE name = ?", params![name])?;
+            .query_row(
+                &format!("SELECT {} FROM names WHERE name = ?", INFO_COLUMNS),
+                params![name],
+                |row| Ok(key_info(row)),
+            )
+            .optional()?
+            .ok_or_else(|| Error::KeyNotFound(name.to_string()))?
+    }
+
+    pub fn list(&self) -> Result<Vec<KeyInfo>, Error> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT {} FROM names ORDER BY id", INFO_COLUMNS))?;
+        let rows = stmt.query_and_then([], key_info)?;
+        let mut keys = Vec::new();
+        for row in rows {
+            keys.push(row?);
+        }
+        Ok(keys)
+    }
+
+    pub fn set_watch_only(&self, name: &str, public_key: &PublicKey) -> Result<(), Error> {
+        let tx = self.write_transaction()?;
+        ensure_name_free(&tx, name)?;
+        tx.execute(
+            "INSERT OR ABORT INTO watch_only (name, scheme, public_key, created_at)
+                    VALUES (?, ?, ?, ?)",
+            params![
+                name,
+                Scheme::from(public_key).to_string(),
+                essential_signer::public_key_to_bytes(public_key),
+                info::now()
+            ],
+        )
+        .map_err(|e| match e.sqlite_error_code() {
+            Some(ErrorCode::ConstraintViolation) => Error::DuplicateKeyName(name.to_string()),
+            _ => e.into(),
+        })?;
+        tx.commit()?;
         Ok(())
     }
 
-    pub fn list(&self) -> anyhow::Result<Vec<(String, Scheme)>> {
-        let mut stmt = self.conn.prepare("SELECT name, scheme FROM names")?;
-        let rows = stmt.query_and_then([], |row| {
-            let name = row.get(0)?;
-            let scheme: String = row.get(1)?;
-            anyhow::Ok((name, Scheme::from_str(&scheme)?))
+    pub fn get_watch_only(&self, name: &str) -> Result<WatchOnlyKey, Error> {
+        self.conn
+            .query_row(
+                "SELECT name, public_key, created_at FROM watch_only WHERE name = ?",
+                params![name],
+                |row| Ok(watch_only_key(row)),
+            )
+            .optional()?
+            .ok_or_else(|| Error::KeyNotFound(name.to_string()))?
+    }
+
+    pub fn delete_watch_only(&self, name: &str) -> Result<(), Error> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM watch_only WHERE name = ?", params![name])?;
+        if deleted == 0 {
+            return Err(Error::KeyNotFound(name.to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn list_watch_only(&self) -> Result<Vec<WatchOnlyKey>, Error> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, public_key, created_at FROM watch_only ORDER BY id")?;
+        let rows = stmt.query_and_then([], watch_only_key)?;
+        let mut keys = Vec::new();
+        for row in rows {
+            keys.push(row?);
+        }
+        Ok(keys)
+    }
+
+    /// The names and public keys of all key pairs then all watch-only keys,
+    /// each in the order they were stored.
+    ///
+    /// Entries whose public key can't be read are skipped.
+    pub fn public_keys(&self) -> Result<Vec<(String, PublicKey)>, Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT name, public_key, 0 AS kind, id FROM names
+            UNION ALL SELECT name, public_key, 1, id FROM watch_only
+            ORDER BY kind, id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<Vec<u8>>>(1)?))
         })?;
-        let mut names = Vec::new();
+        let mut keys = Vec::new();
         for row in rows {
-            names.push(row?);
+            let (name, public_key) = row?;
+            let public_key =
+                public_key.and_then(|key| essential_signer::public_key_from_bytes(&key).ok());
+            if let Some(public_key) = public_key {
+                keys.push((name, public_key));
+            }
         }
-        Ok(names)
+        Ok(keys)
+    }
+
+    /// Start a transaction that takes the write lock immediately
+    /// so nothing else can write between its reads and writes.
+    fn write_transaction(&self) -> rusqlite::Result<Transaction<'_>> {
+        Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate)
     }
+
+    pub fn set_seed(&self, seed: &SecretBytes) -> Result<(), Error> {
+        self.conn
+            .execute(
+                "INSERT OR ABORT INTO seed (id, secret) VALUES (0, ?)",
+                params![seed.expose()],
+            )
+            .map_err(|e| match e.sqlite_error_code() {
+                Some(ErrorCode::ConstraintViolation) => Error::SeedExists,
+                _ => e.into(),
+            })?;
+        Ok(())
+    }
+
+    pub fn get_seed(&self) -> Result<SecretBytes, Error> {
+        self.conn
+            .query_row("SELECT secret FROM seed WHERE id = 0", [], |row| {
+                Ok(SecretBytes::new(row.get(0)?))
+            })
+            .optional()?
+            .ok_or(Error::NoSeed)
+    }
+}
+
+const INFO_COLUMNS: &str =
+    "name, scheme, path, created_at, updated_at, last_used_at, description, tags,
+    allowed_operations, max_input_size, expires_at, require_confirmation";
+
+fn key_info(row: &rusqlite::Row) -> Result<KeyInfo, Error> {
+    let name: String = row.get(0)?;
+    let scheme: String = row.get(1)?;
+    let path: Option<String> = row.get(2)?;
+    let tags: Option<String> = row.get(7)?;
+    let allowed_operations: Option<String> = row.get(8)?;
+    let invalid = |field| Error::InvalidStoredMetadata(name.clone(), field);
+    let tags = match tags {
+        Some(tags) => serde_json::from_str(&tags).map_err(|_| invalid("tags"))?,
+        None => Vec::new(),
+    };
+    let allowed_operations = allowed_operations
+        .map(|ops| {
+            let ops: Vec<String> =
+                serde_json::from_str(&ops).map_err(|_| invalid("allowed operations"))?;
+            ops.iter().map(|op| SignOperation::from_str(op)).collect()
+        })
+        .transpose()?;
+    Ok(KeyInfo {
+        name,
+        scheme: Scheme::from_str(&scheme)?,
+        derivation_path: path.map(|p| p.parse()).transpose()?,
+        created_at: info::from_unix(row.get(3)?),
+        updated_at: info::from_unix(row.get(4)?),
+        last_used_at: info::from_unix(row.get(5)?),
+        description: row.get(6)?,
+        tags,
+        policy: SigningPolicy {
+            allowed_operations,
+            max_input_size: row.get(9)?,
+            expires_at: info::from_unix(row.get(10)?),
+            require_confirmation: row.get(11)?,
+        },
+    })
+}
+
+fn watch_only_key(row: &rusqlite::Row) -> Result<WatchOnlyKey, Error> {
+    let public_key: Vec<u8> = row.get(1)?;
+    Ok(WatchOnlyKey {
+        name: row.get(0)?,
+        public_key: essential_signer::public_key_from_bytes(&public_key)?,
+        created_at: info::from_unix(row.get(2)?),
+    })
+}
+
+/// The public key of the secret if it is a valid key of the scheme.
+fn public_key_bytes(scheme: Scheme, secret: &SecretBytes) -> Option<Vec<u8>> {
+    crate::secret_to_key(secret, scheme)
+        .map(|key| essential_signer::public_key_to_bytes(&essential_signer::public_key(&key)))
+}
+
+fn audit_entry(row: &rusqlite::Row) -> Result<AuditEntry, Error> {
+    let id: i64 = row.get(0)?;
+    let scheme: String = row.get(3)?;
+    let operation: String = row.get(4)?;
+    let hash = |i| -> Result<Hash, Error> {
+        row.get::<_, Vec<u8>>(i)?
+            .try_into()
+            .map_err(|_| Error::AuditLogTampered(id as u64))
+    };
+    Ok(AuditEntry {
+        id: id as u64,
+        time: info::from_unix(row.get(1)?),
+        name: row.get(2)?,
+        scheme: Scheme::from_str(&scheme)?,
+        operation: SignOperation::from_str(&operation)?,
+        input_hash: hash(5)?,
+        signature_hash: hash(6)?,
+    })
+}
+
+/// Key pairs and watch-only keys share names so a name always means one key.
+///
+/// Checked inside the transaction that takes the name
+/// so the name can't be taken between the check and the write.
+fn ensure_name_free(tx: &Transaction, name: &str) -> Result<(), Error> {
+    let taken: bool = tx.query_row(
+        "SELECT EXISTS (SELECT 1 FROM names WHERE name = ?1)
+            OR EXISTS (SELECT 1 FROM watch_only WHERE name = ?1)",
+        params![name],
+        |row| row.get(0),
+    )?;
+    if taken {
+        return Err(Error::DuplicateKeyName(name.to_string()));
+    }
+    Ok(())
+}
+
+/// The highest id ever used in the audit log.
+fn audit_sequence(conn: &Connection) -> Result<i64, Error> {
+    Ok(conn
+        .query_row(
+            "SELECT seq FROM sqlite_sequence WHERE name = 'audit'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?
+        .unwrap_or(0))
+}
+
+fn unlock(key: &DbKey, mut conn: Connection) -> Result<Connection, Error> {
+    conn.pragma_update(None, "key", key.pragma_value().as_str())?;
+    conn.pragma_update(None, "cipher_memory_security", "ON")?;
+    conn.query_row("SELECT COUNT(*) FROM `sqlite_master`;", [], |_row| Ok(()))
+        .map_err(|e| match e.sqlite_error_code() {
+            Some(ErrorCode::NotADatabase) => Error::WrongPassword,
+            _ => e.into(),
+        })?;
+    migrate(&mut conn)?;
+    Ok(conn)
 }
 
-fn unlock(key: &[u8], mut conn: Connection) -> anyhow::Result<Connection> {
-    let tx = conn.transaction()?;
-    tx.pragma_update(None, "key", hex::encode(key))?;
-    tx.pragma_update(None, "cipher_memory_security", "ON")?;
-    tx.query_row("SELECT COUNT(*) FROM `sqlite_master`;", [], |_row| Ok(()))?;
+type Migration = fn(&Transaction) -> rusqlite::Result<()>;
+
+/// Migrations in the order they are applied.
+///
+/// The schema version is the number of migrations applied.
+/// Only ever append to this list.
+///
+/// Wallets created before the schema was versioned start at version 0
+/// but may already have some of these changes,
+/// so the migrations up to version 4 check before changing anything.
+const MIGRATIONS: &[Migration] = &[
+    create_names,
+    add_derivation_paths,
+    create_seed,
+    add_key_metadata,
+    create_audit,
+    add_signing_policies,
+    create_watch_only,
+    add_public_keys,
+];
+
+/// Upgrade the database to the latest schema version.
+///
+/// All pending migrations are applied in one transaction
+/// so a failed upgrade leaves the database unchanged.
+/// The write lock is taken up front so the wallet can be opened while it is in use.
+/// Returns an error if the database is from a newer version of the wallet.
+fn migrate(conn: &mut Connection) -> Result<(), Error> {
+    let latest = MIGRATIONS.len() as i64;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            id INTEGER PRIMARY KEY CHECK (id = 0),
+            version INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    let version: i64 = tx
+        .query_row(
+            "SELECT version FROM schema_version WHERE id = 0",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?
+        .unwrap_or(0);
+    if version > latest {
+        return Err(Error::UnsupportedSchemaVersion(version, latest));
+    }
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(&tx)?;
+    }
+    tx.execute(
+        "INSERT OR REPLACE INTO schema_version (id, version) VALUES (0, ?)",
+        params![latest],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Version 1.
+///
+/// Wallets from before schema versions already have this table.
+fn create_names(tx: &Transaction) -> rusqlite::Result<()> {
     tx.execute(
         "CREATE TABLE IF NOT EXISTS names (
             id INTEGER PRIMARY KEY,
@@ -72,6 +640,412 @@ fn unlock(key: &[u8], mut conn: Connection) -> anyhow::Result<Connection> {
         )",
         [],
     )?;
-    tx.commit()?;
-    Ok(conn)
+    Ok(())
+}
+
+/// Version 2.
+fn add_derivation_paths(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute("ALTER TABLE names ADD COLUMN path TEXT", [])?;
+    Ok(())
+}
+
+/// Version 3.
+fn create_seed(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE seed (
+            id INTEGER PRIMARY KEY CHECK (id = 0),
+            secret BLOB NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Version 4.
+fn add_key_metadata(tx: &Transaction) -> rusqlite::Result<()> {
+    for column in [
+        "created_at INTEGER",
+        "updated_at INTEGER",
+        "last_used_at INTEGER",
+        "description TEXT",
+        "tags TEXT",
+    ] {
+        tx.execute(&format!("ALTER TABLE names ADD COLUMN {}", column), [])?;
+    }
+    Ok(())
+}
+
+/// Version 5.
+fn create_audit(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE audit (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            scheme TEXT NOT NULL,
+            operation TEXT NOT NULL,
+            input_hash BLOB NOT NULL,
+            signature_hash BLOB NOT NULL,
+            prev_hash BLOB NOT NULL,
+            hash BLOB NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Version 6.
+fn add_signing_policies(tx: &Transaction) -> rusqlite::Result<()> {
+    for column in [
+        "allowed_operations TEXT",
+        "max_input_size INTEGER",
+        "expires_at INTEGER",
+        "require_confirmation INTEGER NOT NULL DEFAULT 0",
+    ] {
+        tx.execute(&format!("ALTER TABLE names ADD COLUMN {}", column), [])?;
+    }
+    Ok(())
+}
+
+/// Version 7.
+fn create_watch_only(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE watch_only (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            scheme TEXT NOT NULL,
+            public_key BLOB NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Version 8.
+///
+/// Stores the public keys of key pairs so lookups don't decrypt the private keys.
+fn add_public_keys(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute("ALTER TABLE names ADD COLUMN public_key BLOB", [])?;
+    let mut stmt = tx.prepare("SELECT id, scheme, secret FROM names")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            SecretBytes::new(row.get(2)?),
+        ))
+    })?;
+    let mut keys = Vec::new();
+    for row in rows {
+        let (id, scheme, secret) = row?;
+        // Keys that can't be read are left without a public key.
+        if let Some(public_key) = Scheme::from_str(&scheme)
+            .ok()
+            .and_then(|scheme| public_key_bytes(scheme, &secret))
+        {
+            keys.push((id, public_key));
+        }
+    }
+    for (id, public_key) in keys {
+        tx.execute(
+            "UPDATE names SET public_key = ? WHERE id = ?",
+            params![public_key, id],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> (tempfile::TempDir, Store) {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(&KEY, &dir.path().join("db")).unwrap();
+        (dir, store)
+    }
+
+    /// A store with a key pair that has signed three times.
+    fn signed_store() -> (tempfile::TempDir, Store) {
+        let (dir, store) = store();
+        let secret = SecretBytes::new(vec![1; 32]);
+        store.set_secret("key", Scheme::Secp256k1, &secret).unwrap();
+        for i in 0..3 {
+            store
+                .record_signature(
+                    "key",
+                    Scheme::Secp256k1,
+                    SignOperation::Bytes,
+                    &[i; 32],
+                    &[i + 10; 32],
+                )
+                .unwrap();
+        }
+        (dir, store)
+    }
+
+    const KEY: DbKey = DbKey::Raw(&[1; 32]);
+
+    /// Names table of wallets from before schema versions.
+    const BASELINE_NAMES: &str = "CREATE TABLE names (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE,
+        scheme TEXT NOT NULL,
+        secret BLOB NOT NULL
+    )";
+
+    /// Create an unversioned database with one key pair.
+    fn legacy() -> (tempfile::TempDir, std::path::PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db");
+        let conn = Connection::open(&path).unwrap();
+        conn.pragma_update(None, "key", KEY.pragma_value().as_str())
+            .unwrap();
+        conn.execute_batch(BASELINE_NAMES).unwrap();
+        conn.execute(
+            "INSERT INTO names (name, scheme, secret) VALUES ('old', 'secp256k1', ?)",
+            params![vec![1u8; 32]],
+        )
+        .unwrap();
+        (dir, path)
+    }
+
+    fn schema_version(store: &Store) -> i64 {
+        store
+            .conn
+            .query_row("SELECT version FROM schema_version", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    /// Open a legacy database and check it works like a new one.
+    #[test]
+    fn migrate_baseline() {
+        let (_dir, path) = legacy();
+        let store = Store::new(&KEY, &path).unwrap();
+        assert_eq!(schema_version(&store), MIGRATIONS.len() as i64);
+
+        let (secret, scheme) = store.get_secret("old").unwrap();
+        assert_eq!(secret.expose(), [1; 32]);
+        assert_eq!(scheme, Scheme::Secp256k1);
+        let info = store.key_info("old").unwrap();
+        assert_eq!(info.derivation_path, None);
+        assert_eq!(
+            store.public_keys().unwrap(),
+            [("old".to_string(), public_key_of(&secret, scheme))]
+        );
+        assert!(info.tags.is_empty());
+        assert!(info.policy.is_unrestricted());
+
+        store.set_tags("old", &["tag".to_string()]).unwrap();
+        store
+            .record_signature("old", scheme, SignOperation::Hash, &[0; 32], &[0; 32])
+            .unwrap();
+        assert_eq!(store.verify_audit_log().unwrap(), 1);
+        store.set_seed(&SecretBytes::new(vec![2; 64])).unwrap();
+        let secret_key = essential_signer::secp256k1::SecretKey::from_slice(&[2; 32]).unwrap();
+        let public_key =
+            PublicKey::Secp256k1(secret_key.public_key(essential_signer::secp256k1::SECP256K1));
+        store.set_watch_only("watched", &public_key).unwrap();
+        assert_eq!(
+            store.get_watch_only("watched").unwrap().public_key,
+            public_key
+        );
+        drop(store);
+
+        // Opening again does not migrate twice.
+        let store = Store::new(&KEY, &path).unwrap();
+        assert_eq!(store.key_info("old").unwrap().tags, ["tag"]);
+    }
+
+    #[test]
+    fn newer_schema_is_rejected() {
+        let (dir, store) = store();
+        let path = dir.path().join("db");
+        store
+            .conn
+            .execute("UPDATE schema_version SET version = 99", [])
+            .unwrap();
+        drop(store);
+        let before = std::fs::read(&path).unwrap();
+        assert!(matches!(
+            Store::new(&KEY, &path),
+            Err(Error::UnsupportedSchemaVersion(99, latest)) if latest == MIGRATIONS.len() as i64
+        ));
+        assert_eq!(std::fs::read(&path).unwrap(), before);
+    }
+
+    #[test]
+    fn names_are_shared() {
+        let (_dir, store) = signed_store();
+        let secret = SecretBytes::new(vec![2; 32]);
+        store.set_secret("other", Scheme::Ed25519, &secret).unwrap();
+        let secret_key = essential_signer::secp256k1::SecretKey::from_slice(&[2; 32]).unwrap();
+        let public_key =
+            PublicKey::Secp256k1(secret_key.public_key(essential_signer::secp256k1::SECP256K1));
+        store.set_watch_only("watched", &public_key).unwrap();
+
+        let duplicate = |r: Result<(), Error>, name: &str| {
+            assert!(matches!(r, Err(Error::DuplicateKeyName(n)) if n == name));
+        };
+        duplicate(
+            store.set_secret("watched", Scheme::Ed25519, &secret),
+            "watched",
+        );
+        duplicate(store.set_secret("key", Scheme::Ed25519, &secret), "key");
+        duplicate(store.set_watch_only("key", &public_key), "key");
+        duplicate(store.set_watch_only("watched", &public_key), "watched");
+        duplicate(store.rename_secret("key", "watched"), "watched");
+        duplicate(store.rename_secret("key", "other"), "other");
+
+        store.rename_secret("key", "renamed").unwrap();
+        assert!(matches!(
+            store.get_secret("key"),
+            Err(Error::KeyNotFound(_))
+        ));
+        store.get_secret("renamed").unwrap();
+        // Failed writes leave no transaction open.
+        store.set_secret("new", Scheme::Ed25519, &secret).unwrap();
+    }
+
+    fn public_key_of(secret: &SecretBytes, scheme: Scheme) -> PublicKey {
+        essential_signer::public_key(&crate::secret_to_key(secret, scheme).unwrap())
+    }
+
+    #[test]
+    fn public_keys_skip_unreadable() {
+        let (_dir, store) = store();
+        let secret = SecretBytes::new(vec![1; 32]);
+        store.set_secret("key", Scheme::Secp256k1, &secret).unwrap();
+        store
+            .set_secret("short", Scheme::Ed25519, &SecretBytes::new(vec![2; 31]))
+            .unwrap();
+        store
+            .set_secret("corrupt", Scheme::Ed25519, &secret)
+            .unwrap();
+        store
+            .conn
+            .execute(
+                "UPDATE names SET public_key = x'00' WHERE name = 'corrupt'",
+                [],
+            )
+            .unwrap();
+        let watched = public_key_of(&SecretBytes::new(vec![3; 32]), Scheme::Ed25519);
+        store.set_watch_only("watched", &watched).unwrap();
+        store
+            .conn
+            .execute(
+                "INSERT INTO watch_only (name, scheme, public_key, created_at)
+                VALUES ('bad', 'ed25519', x'00', 0)",
+                [],
+            )
+            .unwrap();
+
+        assert_eq!(
+            store.public_keys().unwrap(),
+            [
+                ("key".to_string(), public_key_of(&secret, Scheme::Secp256k1)),
+                ("watched".to_string(), watched),
+            ]
+        );
+    }
+
+    #[test]
+    fn corrupt_metadata() {
+        let (_dir, store) = signed_store();
+        for (column, field) in [
+            ("tags", "tags"),
+            ("allowed_operations", "allowed operations"),
+        ] {
+            store
+                .conn
+                .execute(&format!("UPDATE names SET {} = 'not json'", column), [])
+                .unwrap();
+            assert!(matches!(
+                store.key_info("key"),
+                Err(Error::InvalidStoredMetadata(name, f)) if name == "key" && f == field
+            ));
+            assert!(store.list().is_err());
+            store
+                .conn
+                .execute(&format!("UPDATE names SET {} = NULL", column), [])
+                .unwrap();
+        }
+        store.key_info("key").unwrap();
+    }
+
+    fn tampered_at(store: &Store) -> u64 {
+        match store.verify_audit_log() {
+            Err(Error::AuditLogTampered(id)) => id,
+            r => panic!("expected the log to be tampered, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn audit_log_intact() {
+        let (_dir, store) = signed_store();
+        assert_eq!(store.verify_audit_log().unwrap(), 3);
+        let log = store.audit_log(Some("key")).unwrap();
+        assert_eq!(log.len(), 3);
+        assert_eq!(log[1].input_hash, [1; 32]);
+        assert!(store.audit_log(Some("other")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn audit_log_changed_entry() {
+        let (_dir, store) = signed_store();
+        store
+            .conn
+            .execute("UPDATE audit SET operation = 'hash' WHERE id = 2", [])
+            .unwrap();
+        assert_eq!(tampered_at(&store), 2);
+    }
+
+    #[test]
+    fn audit_log_deleted_middle_entry() {
+        let (_dir, store) = signed_store();
+        store
+            .conn
+            .execute("DELETE FROM audit WHERE id = 2", [])
+            .unwrap();
+        assert_eq!(tampered_at(&store), 2);
+    }
+
+    #[test]
+    fn audit_log_deleted_last_entry() {
+        let (_dir, store) = signed_store();
+        store
+            .conn
+            .execute("DELETE FROM audit WHERE id = 3", [])
+            .unwrap();
+        assert_eq!(tampered_at(&store), 3);
+    }
+
+    #[test]
+    fn concurrent_signatures_extend_one_chain() {
+        let (dir, store) = signed_store();
+        let path = dir.path().join("db");
+        let threads: Vec<_> = (0..4u8)
+            .map(|t| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let store = Store::new(&KEY, &path).unwrap();
+                    for i in 0..10 {
+                        store
+                            .record_signature(
+                                "key",
+                                Scheme::Secp256k1,
+                                SignOperation::Hash,
+                                &[t; 32],
+                                &[i; 32],
+                            )
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(store.verify_audit_log().unwrap(), 43);
+    }
 }
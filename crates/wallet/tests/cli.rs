@@ -0,0 +1,76 @@
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+use essential_wallet::{KdfParams, Scheme, Wallet};
+
+fn wallet() -> (tempfile::TempDir, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("wallet");
+    let params = KdfParams {
+        memory_kib: 8,
+        iterations: 1,
+        parallelism: 1,
+    };
+    let mut wallet = Wallet::create_with_kdf("password", path.clone(), params).unwrap();
+    wallet.new_key_pair("key", Scheme::Secp256k1).unwrap();
+    wallet.new_key_pair("other", Scheme::Ed25519).unwrap();
+    (dir, path)
+}
+
+fn run(path: &Path, args: &[&str]) -> Output {
+    std::process::Command::new(env!("CARGO_BIN_EXE_essential-wallet"))
+        .arg("--path")
+        .arg(path)
+        .args(["--password", "password"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: Output) -> String {
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn which() {
+    let (_dir, path) = wallet();
+    let public_key = stdout(run(&path, &["print-pub-key", "key"]));
+    let hash = stdout(run(&path, &["print-pub-key", "--hashed", "key"]));
+
+    let which = |args: &[&str]| stdout(run(&path, args));
+    assert_eq!(which(&["which", "public-key", public_key.trim()]), "key\n");
+    assert_eq!(which(&["which", "hash", hash.trim()]), "key\n");
+
+    let output = run(&path, &["which", "hash", &"00".repeat(32)]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn verify() {
+    let (_dir, path) = wallet();
+    let data = "07".repeat(16);
+    for name in ["key", "other"] {
+        let sig = stdout(run(&path, &["sign", name, "data", &data]));
+        let public_key = stdout(run(&path, &["print-pub-key", name]));
+        let verify = |args: &[&str], data: &str| {
+            let mut args = [&["verify", sig.trim()], args].concat();
+            args.extend(["data", data]);
+            run(&path, &args)
+        };
+        let by_name = ["--name", name];
+        let by_key = ["--public-key", public_key.trim()];
+
+        for args in [by_name, by_key] {
+            assert_eq!(stdout(verify(&args, &data)), "Signature is valid\n");
+            let output = verify(&args, &"08".repeat(16));
+            assert!(!output.status.success());
+            assert!(output.stdout.is_empty());
+        }
+    }
+
+    let key_sig = stdout(run(&path, &["sign", "key", "data", &data]));
+    let output = run(&path, &["verify", key_sig.trim(), "--name", "other", "data", &data]);
+    assert!(!output.status.success());
+}
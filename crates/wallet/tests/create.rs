@@ -0,0 +1,200 @@
+use std::path::Path;
+
+use essential_wallet::{Error, KdfParams, Scheme, Wallet};
+
+const PARAMS: KdfParams = KdfParams {
+    memory_kib: 8,
+    iterations: 1,
+    parallelism: 1,
+};
+
+fn files(dir: &Path) -> Vec<String> {
+    let mut files: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn create_and_open() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("wallet");
+    Wallet::create_with_kdf("password", path.clone(), PARAMS).unwrap();
+    assert!(Wallet::exists(&path));
+    assert_eq!(files(&path), ["accounts.kdf.json", "accounts.sqlite3"]);
+    assert!(Wallet::create_with_kdf("password", path.clone(), PARAMS).is_err());
+    Wallet::open("password", path.clone()).unwrap();
+    assert!(Wallet::open("wrong", path).is_err());
+}
+
+#[test]
+fn open_missing_wallet() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("wallet");
+    assert!(matches!(
+        Wallet::open("password", path.clone()),
+        Err(Error::WalletNotFound(p)) if p == path
+    ));
+    assert!(!path.exists());
+}
+
+#[test]
+fn change_password() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("wallet");
+    let mut wallet = Wallet::create_with_kdf("password", path.clone(), PARAMS).unwrap();
+    wallet.new_key_pair("key", Scheme::Secp256k1).unwrap();
+    let public_key = wallet.get_public_key("key").unwrap();
+    wallet.change_password("new").unwrap();
+    drop(wallet);
+
+    assert!(matches!(
+        Wallet::open("password", path.clone()),
+        Err(Error::WrongPassword)
+    ));
+    let mut wallet = Wallet::open("new", path).unwrap();
+    assert_eq!(wallet.get_public_key("key").unwrap(), public_key);
+}
+
+#[cfg(unix)]
+#[test]
+fn failed_create_leaves_no_header() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("wallet");
+    std::fs::create_dir(&path).unwrap();
+    // A dangling link is not a wallet but the database can't be created through it.
+    std::os::unix::fs::symlink(dir.path().join("missing/db"), path.join("accounts.sqlite3"))
+        .unwrap();
+    assert!(!Wallet::exists(&path));
+    assert!(Wallet::create_with_kdf("password", path.clone(), PARAMS).is_err());
+    assert_eq!(files(&path), ["accounts.sqlite3"]);
+}
+
+#[test]
+fn hostile_header_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("wallet");
+    Wallet::create_with_kdf("password", path.clone(), PARAMS).unwrap();
+    let header = path.join("accounts.kdf.json");
+    for (param, max) in [
+        ("memory_kib", KdfParams::MAX_MEMORY_KIB),
+        ("iterations", KdfParams::MAX_ITERATIONS),
+        ("parallelism", KdfParams::MAX_PARALLELISM),
+    ] {
+        let json = std::fs::read(&header).unwrap();
+        let mut hostile: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        hostile[param] = (max + 1).into();
+        std::fs::write(&header, serde_json::to_vec(&hostile).unwrap()).unwrap();
+        assert!(matches!(
+            Wallet::open("password", path.clone()),
+            Err(Error::InvalidKdfHeader(_))
+        ));
+        std::fs::write(&header, json).unwrap();
+    }
+    Wallet::open("password", path).unwrap();
+}
+
+#[test]
+fn too_large_params_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("wallet");
+    let params = KdfParams {
+        iterations: KdfParams::MAX_ITERATIONS + 1,
+        ..PARAMS
+    };
+    assert!(matches!(
+        Wallet::create_with_kdf("password", path.clone(), params),
+        Err(Error::KdfParamTooLarge("iterations", _, _))
+    ));
+    assert!(!Wallet::exists(&path));
+
+    let mut wallet = Wallet::create_with_kdf("password", path.clone(), PARAMS).unwrap();
+    let err = wallet.change_password_with_kdf("new", params).unwrap_err();
+    assert!(matches!(err, Error::KdfParamTooLarge("iterations", _, _)));
+    drop(wallet);
+    Wallet::open("password", path).unwrap();
+}
+
+/// Create a wallet like the versions before the KDF header
+/// which keyed the database with the password itself.
+fn create_legacy(path: &Path) {
+    std::fs::create_dir_all(path).unwrap();
+    let conn = rusqlite::Connection::open(path.join("accounts.sqlite3")).unwrap();
+    conn.pragma_update(None, "key", hex::encode("password"))
+        .unwrap();
+    conn.execute_batch(
+        "CREATE TABLE names (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            scheme TEXT NOT NULL,
+            secret BLOB NOT NULL
+        )",
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO names (name, scheme, secret) VALUES ('old', 'ed25519', ?)",
+        [vec![2u8; 32]],
+    )
+    .unwrap();
+}
+
+#[test]
+fn legacy_wallet_is_migrated() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("wallet");
+    create_legacy(&path);
+    let expected = essential_signer::public_key(&essential_signer::Key::Ed25519([2; 32]));
+
+    assert!(matches!(
+        Wallet::open("wrong", path.clone()),
+        Err(Error::WrongPassword)
+    ));
+    assert_eq!(files(&path), ["accounts.sqlite3"]);
+
+    let mut wallet = Wallet::open("password", path.clone()).unwrap();
+    assert_eq!(files(&path), ["accounts.kdf.json", "accounts.sqlite3"]);
+    assert_eq!(wallet.get_public_key("old").unwrap(), expected);
+    drop(wallet);
+
+    // The database is now keyed through the header.
+    let mut wallet = Wallet::open("password", path.clone()).unwrap();
+    assert_eq!(wallet.get_public_key("old").unwrap(), expected);
+    assert!(matches!(
+        Wallet::open("wrong", path),
+        Err(Error::WrongPassword)
+    ));
+}
+
+#[test]
+fn interrupted_rekey_is_recovered() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("wallet");
+    let header = path.join("accounts.kdf.json");
+    let pending = path.join("accounts.kdf.json.new");
+    let mut wallet = Wallet::create_with_kdf("password", path.clone(), PARAMS).unwrap();
+    let old = std::fs::read(&header).unwrap();
+    wallet.change_password("new").unwrap();
+    drop(wallet);
+
+    // Interrupted after the database was rekeyed but before the header was replaced.
+    std::fs::rename(&header, &pending).unwrap();
+    std::fs::write(&header, &old).unwrap();
+    assert!(matches!(
+        Wallet::open("password", path.clone()),
+        Err(Error::WrongPassword)
+    ));
+    Wallet::open("new", path.clone()).unwrap();
+    assert_eq!(files(&path), ["accounts.kdf.json", "accounts.sqlite3"]);
+    Wallet::open("new", path.clone()).unwrap();
+
+    // Interrupted before the database was rekeyed.
+    std::fs::write(&pending, &old).unwrap();
+    Wallet::open("new", path.clone()).unwrap();
+    assert_eq!(files(&path), ["accounts.kdf.json", "accounts.sqlite3"]);
+    assert!(matches!(
+        Wallet::open("password", path),
+        Err(Error::WrongPassword)
+    ));
+}
@@ -0,0 +1,62 @@
+use essential_wallet::{Error, ExportFormat, KdfParams, Scheme, Wallet};
+
+fn wallet() -> (tempfile::TempDir, Wallet) {
+    let dir = tempfile::tempdir().unwrap();
+    let params = KdfParams {
+        memory_kib: 8,
+        iterations: 1,
+        parallelism: 1,
+    };
+    let wallet = Wallet::create_with_kdf("password", dir.path().join("wallet"), params).unwrap();
+    (dir, wallet)
+}
+
+#[test]
+fn duplicate_key_name() {
+    let (_dir, mut wallet) = wallet();
+    wallet.new_key_pair("key", Scheme::Secp256k1).unwrap();
+    wallet.new_key_pair("other", Scheme::Ed25519).unwrap();
+    assert!(matches!(
+        wallet.new_key_pair("key", Scheme::Ed25519),
+        Err(Error::DuplicateKeyName(name)) if name == "key"
+    ));
+    assert!(matches!(
+        wallet.rename_key_pair("other", "key"),
+        Err(Error::DuplicateKeyName(name)) if name == "key"
+    ));
+}
+
+#[test]
+fn key_not_found() {
+    let (_dir, mut wallet) = wallet();
+    let not_found = |result| matches!(result, Err(Error::KeyNotFound(name)) if name == "missing");
+    assert!(not_found(wallet.get_public_key("missing").map(drop)));
+    assert!(not_found(wallet.lookup_public_key("missing").map(drop)));
+    assert!(not_found(wallet.sign_hash([0; 32], "missing").map(drop)));
+    assert!(not_found(wallet.delete_key_pair("missing")));
+    assert!(not_found(wallet.rename_key_pair("missing", "new")));
+}
+
+#[test]
+fn wrong_password() {
+    let (dir, wallet) = wallet();
+    drop(wallet);
+    assert!(matches!(
+        Wallet::open("wrong", dir.path().join("wallet")),
+        Err(Error::WrongPassword)
+    ));
+}
+
+#[test]
+fn signer_errors() {
+    let (_dir, mut wallet) = wallet();
+    wallet.new_key_pair("key", Scheme::Ed25519).unwrap();
+    assert!(matches!(
+        wallet.sign_aligned_bytes(&[0; 7], "key"),
+        Err(Error::Signer(essential_signer::Error::NotWordAligned))
+    ));
+    assert!(matches!(
+        wallet.export_key("key", ExportFormat::KeystoreV3, Some("passphrase")),
+        Err(Error::UnsupportedScheme(Scheme::Ed25519, _))
+    ));
+}
@@ -0,0 +1,113 @@
+use essential_wallet::{Encoding, Error, ExportFormat, KdfParams, Scheme, Wallet};
+use pkcs8::{
+    der::{pem::LineEnding, Decode, Encode, SecretDocument},
+    ObjectIdentifier, PrivateKeyInfo,
+};
+use sec1::{der::pem::PemLabel, EcParameters, EcPrivateKey};
+
+const SECP256K1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.132.0.10");
+
+fn wallet() -> (tempfile::TempDir, Wallet) {
+    let dir = tempfile::tempdir().unwrap();
+    let params = KdfParams {
+        memory_kib: 8,
+        iterations: 1,
+        parallelism: 1,
+    };
+    let mut wallet =
+        Wallet::create_with_kdf("password", dir.path().join("wallet"), params).unwrap();
+    wallet.new_key_pair("secp", Scheme::Secp256k1).unwrap();
+    wallet.new_key_pair("ed", Scheme::Ed25519).unwrap();
+    (dir, wallet)
+}
+
+fn secret(wallet: &mut Wallet, name: &str) -> Vec<u8> {
+    wallet
+        .get_private_key(name)
+        .unwrap()
+        .secret_bytes()
+        .expose()
+        .to_vec()
+}
+
+#[test]
+fn pkcs8_round_trip() {
+    let (_dir, mut wallet) = wallet();
+    for (name, scheme) in [("secp", Scheme::Secp256k1), ("ed", Scheme::Ed25519)] {
+        let pem = wallet.export_key(name, ExportFormat::Pkcs8, None).unwrap();
+        let imported = format!("{}-pkcs8", name);
+        let found = wallet
+            .import_key(&imported, pem.as_bytes(), Encoding::Hex, None)
+            .unwrap();
+        assert_eq!(found, scheme);
+        assert_eq!(secret(&mut wallet, &imported), secret(&mut wallet, name));
+    }
+}
+
+#[test]
+fn encrypted_pkcs8_round_trip() {
+    let (_dir, mut wallet) = wallet();
+    let pem = wallet
+        .export_key("ed", ExportFormat::EncryptedPkcs8, Some("passphrase"))
+        .unwrap();
+    assert!(matches!(
+        wallet.import_key("imported", pem.as_bytes(), Encoding::Hex, None),
+        Err(Error::MissingPassphrase)
+    ));
+    wallet
+        .import_key_with_passphrase(
+            "imported",
+            pem.as_bytes(),
+            Encoding::Hex,
+            None,
+            Some("passphrase"),
+        )
+        .unwrap();
+    assert_eq!(secret(&mut wallet, "imported"), secret(&mut wallet, "ed"));
+}
+
+/// Rewrap the exported key as SEC1 with its curve like `openssl ec` writes.
+#[test]
+fn sec1_round_trip() {
+    let (_dir, mut wallet) = wallet();
+    let pem = wallet
+        .export_key("secp", ExportFormat::Pkcs8, None)
+        .unwrap();
+    let (_, der) = SecretDocument::from_pem(&pem).unwrap();
+    let info = PrivateKeyInfo::from_der(der.as_bytes()).unwrap();
+    let key = EcPrivateKey::from_der(info.private_key).unwrap();
+    let sec1 = EcPrivateKey {
+        parameters: Some(EcParameters::NamedCurve(SECP256K1)),
+        ..key
+    };
+    let sec1 = SecretDocument::try_from(sec1.to_der().unwrap())
+        .unwrap()
+        .to_pem(EcPrivateKey::PEM_LABEL, LineEnding::LF)
+        .unwrap();
+    wallet
+        .import_key("imported", sec1.as_bytes(), Encoding::Hex, None)
+        .unwrap();
+    assert_eq!(secret(&mut wallet, "imported"), secret(&mut wallet, "secp"));
+}
+
+#[test]
+fn keystore_round_trip() {
+    let (_dir, mut wallet) = wallet();
+    for format in [ExportFormat::KeystoreV3, ExportFormat::KeystoreV3Pbkdf2] {
+        let json = wallet
+            .export_key("secp", format, Some("passphrase"))
+            .unwrap();
+        let imported = format!("{:?}", format);
+        assert!(matches!(
+            wallet.import_keystore(&imported, &json, "wrong"),
+            Err(Error::KeystoreMacMismatch)
+        ));
+        wallet
+            .import_keystore(&imported, &json, "passphrase")
+            .unwrap();
+        assert_eq!(secret(&mut wallet, &imported), secret(&mut wallet, "secp"));
+    }
+    assert!(wallet
+        .export_key("ed", ExportFormat::KeystoreV3, Some("passphrase"))
+        .is_err());
+}
@@ -0,0 +1,43 @@
+use essential_wallet::{public_key_hash, KdfParams, Scheme, Wallet};
+
+fn wallet() -> (tempfile::TempDir, Wallet) {
+    let dir = tempfile::tempdir().unwrap();
+    let params = KdfParams {
+        memory_kib: 8,
+        iterations: 1,
+        parallelism: 1,
+    };
+    let wallet = Wallet::create_with_kdf("password", dir.path().join("wallet"), params).unwrap();
+    (dir, wallet)
+}
+
+#[test]
+fn find_key_pairs_and_watch_only_keys() {
+    let (_dir, mut wallet) = wallet();
+    wallet.new_key_pair("secp", Scheme::Secp256k1).unwrap();
+    wallet.new_key_pair("ed", Scheme::Ed25519).unwrap();
+    let secp = wallet.get_public_key("secp").unwrap();
+    let ed = wallet.get_public_key("ed").unwrap();
+    wallet.add_watch_only_key("watched", &secp).unwrap();
+
+    assert_eq!(
+        wallet.find_by_public_key(&secp).unwrap(),
+        ["secp", "watched"]
+    );
+    assert_eq!(wallet.find_by_public_key(&ed).unwrap(), ["ed"]);
+    assert_eq!(
+        wallet.find_by_pubkey_hash(&public_key_hash(&ed)).unwrap(),
+        ["ed"]
+    );
+    assert!(wallet.find_by_pubkey_hash(&[0; 32]).unwrap().is_empty());
+
+    let hash = [5; 32];
+    for (name, expected) in [("secp", ["secp", "watched"].as_slice()), ("ed", &["ed"])] {
+        let sig = wallet.sign_hash(hash, name).unwrap();
+        assert_eq!(wallet.find_by_signature(hash, &sig).unwrap(), expected);
+        assert!(wallet.find_by_signature([6; 32], &sig).unwrap().is_empty());
+    }
+
+    wallet.delete_key_pair("secp").unwrap();
+    assert_eq!(wallet.find_by_public_key(&secp).unwrap(), ["watched"]);
+}
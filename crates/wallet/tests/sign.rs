@@ -0,0 +1,215 @@
+use std::io::Read;
+
+use essential_wallet::{
+    Encoding, Error, HashAlgorithm, KdfParams, Padding, PolicyViolation, Scheme, SignOperation,
+    SigningPolicy, Wallet,
+};
+
+fn wallet() -> (tempfile::TempDir, Wallet) {
+    let dir = tempfile::tempdir().unwrap();
+    let params = KdfParams {
+        memory_kib: 8,
+        iterations: 1,
+        parallelism: 1,
+    };
+    let wallet = Wallet::create_with_kdf("password", dir.path().join("wallet"), params).unwrap();
+    (dir, wallet)
+}
+
+#[test]
+fn sign_with_hasher() {
+    let (_dir, mut wallet) = wallet();
+    let data = vec![7u8; 13];
+    let postcard = (1u64, "essential".to_string());
+    for scheme in [Scheme::Secp256k1, Scheme::Ed25519] {
+        let name = scheme.to_string();
+        wallet.new_key_pair(&name, scheme).unwrap();
+        let pk = wallet.get_public_key(&name).unwrap();
+        let hasher = HashAlgorithm::Keccak256;
+
+        let sig = wallet
+            .sign_bytes_unchecked_with_hasher(&data, hasher, &name)
+            .unwrap();
+        essential_signer::verify_bytes_unchecked_with_hasher(&data, hasher, &sig, &pk).unwrap();
+        assert!(essential_signer::verify_bytes_unchecked(&data, &sig, &pk).is_err());
+
+        let sig = wallet
+            .sign_bytes_with_padding_and_hasher(data.clone(), Padding::End, hasher, &name)
+            .unwrap();
+        essential_signer::verify_bytes_with_padding_and_hasher(
+            data.clone(),
+            Padding::End,
+            hasher,
+            &sig,
+            &pk,
+        )
+        .unwrap();
+
+        let aligned = [7u8; 16];
+        let sig = wallet
+            .sign_aligned_bytes_with_hasher(&aligned, hasher, &name)
+            .unwrap();
+        essential_signer::verify_aligned_bytes_with_hasher(&aligned, hasher, &sig, &pk).unwrap();
+
+        let sig = wallet
+            .sign_postcard_with_hasher(&postcard, hasher, &name)
+            .unwrap();
+        essential_signer::verify_postcard_with_hasher(&postcard, hasher, &sig, &pk).unwrap();
+
+        let sig = wallet
+            .sign_postcard_with_padding_and_hasher(&postcard, Padding::Start, hasher, &name)
+            .unwrap();
+        essential_signer::verify_postcard_with_padding_and_hasher(
+            &postcard,
+            Padding::Start,
+            hasher,
+            &sig,
+            &pk,
+        )
+        .unwrap();
+    }
+}
+
+#[test]
+fn sign_with_reloaded_ed25519_key() {
+    let (dir, mut wallet) = wallet();
+    let key = essential_signer::Key::Ed25519([9; 32]);
+    let expected = essential_signer::public_key(&key);
+    let encoded = key.encode_secret(Encoding::Hex).unwrap();
+    let scheme = wallet
+        .import_key("ed", encoded.as_bytes(), Encoding::Hex, None)
+        .unwrap();
+    assert_eq!(scheme, Scheme::Ed25519);
+    drop(wallet);
+
+    let mut wallet = Wallet::open("password", dir.path().join("wallet")).unwrap();
+    let pk = wallet.get_public_key("ed").unwrap();
+    assert_eq!(pk, expected);
+    let data = [7u8; 16];
+    let sig = wallet.sign_aligned_bytes(&data, "ed").unwrap();
+    assert!(matches!(sig, essential_wallet::Signature::Ed25519(_)));
+    essential_signer::verify_aligned_bytes(&data, &sig, &pk).unwrap();
+}
+
+#[test]
+fn watch_only_key_cannot_sign() {
+    let (_dir, mut wallet) = wallet();
+    wallet.new_key_pair("key", Scheme::Secp256k1).unwrap();
+    let pk = wallet.get_public_key("key").unwrap();
+    wallet.add_watch_only_key("watched", &pk).unwrap();
+    assert!(matches!(
+        wallet.add_watch_only_key("key", &pk),
+        Err(Error::DuplicateKeyName(_))
+    ));
+
+    assert_eq!(wallet.lookup_public_key("watched").unwrap(), pk);
+    assert!(matches!(
+        wallet.sign_hash([0; 32], "watched"),
+        Err(Error::KeyNotFound(name)) if name == "watched"
+    ));
+    assert!(matches!(
+        wallet.get_private_key("watched"),
+        Err(Error::KeyNotFound(_))
+    ));
+    assert!(wallet.audit_log(None).unwrap().is_empty());
+}
+
+/// A reader that fails the test if it is read.
+struct Unread;
+
+impl Read for Unread {
+    fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+        panic!("the input was read before the policy was checked")
+    }
+}
+
+#[test]
+fn sign_reader() {
+    let (_dir, mut wallet) = wallet();
+    wallet.new_key_pair("key", Scheme::Secp256k1).unwrap();
+    let pk = wallet.get_public_key("key").unwrap();
+    let data = [3u8; 13];
+
+    let sig = wallet
+        .sign_reader(&data[..], 13, None, HashAlgorithm::Sha256, "key")
+        .unwrap();
+    essential_signer::verify_bytes_unchecked(&data, &sig, &pk).unwrap();
+
+    let sig = wallet
+        .sign_reader(
+            &data[..],
+            13,
+            Some(Padding::Start),
+            HashAlgorithm::Blake3,
+            "key",
+        )
+        .unwrap();
+    essential_signer::verify_bytes_with_padding_and_hasher(
+        data.to_vec(),
+        Padding::Start,
+        HashAlgorithm::Blake3,
+        &sig,
+        &pk,
+    )
+    .unwrap();
+
+    let err = wallet
+        .sign_reader(&data[..], 12, None, HashAlgorithm::Sha256, "key")
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::Signer(essential_signer::Error::ReaderLengthMismatch(12, 13))
+    ));
+
+    let log = wallet.audit_log(Some("key")).unwrap();
+    assert_eq!(log.len(), 2);
+    assert!(log.iter().all(|e| e.operation == SignOperation::Bytes));
+}
+
+#[test]
+fn sign_reader_checks_policy_first() {
+    let (_dir, mut wallet) = wallet();
+    wallet.new_key_pair("key", Scheme::Ed25519).unwrap();
+
+    let policy = SigningPolicy {
+        max_input_size: Some(12),
+        ..Default::default()
+    };
+    wallet.set_policy("key", &policy).unwrap();
+    let err = wallet
+        .sign_reader(Unread, 13, None, HashAlgorithm::Sha256, "key")
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::PolicyViolation(_, PolicyViolation::InputTooLarge(13, 12))
+    ));
+    // The padded size is checked.
+    let err = wallet
+        .sign_reader(Unread, 9, Some(Padding::End), HashAlgorithm::Sha256, "key")
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::PolicyViolation(_, PolicyViolation::InputTooLarge(16, 12))
+    ));
+    wallet
+        .sign_reader(&[0u8; 12][..], 12, None, HashAlgorithm::Sha256, "key")
+        .unwrap();
+
+    let policy = SigningPolicy {
+        allowed_operations: Some(vec![SignOperation::Hash]),
+        ..Default::default()
+    };
+    wallet.set_policy("key", &policy).unwrap();
+    let err = wallet
+        .sign_reader(Unread, 8, None, HashAlgorithm::Sha256, "key")
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::PolicyViolation(
+            _,
+            PolicyViolation::OperationNotAllowed(SignOperation::Bytes)
+        )
+    ));
+    wallet.sign_hash([0; 32], "key").unwrap();
+    assert_eq!(wallet.audit_log(None).unwrap().len(), 2);
+}